@@ -1,133 +1,1094 @@
 // Sample use:
 // node ping-thing-client.mjs >> ping-thing.log 2>&1 &
 
+import os from 'os';
 import dotenv from 'dotenv';
 import web3 from '@solana/web3.js';
 import bs58 from 'bs58';
-import XMLHttpRequest from 'xhr2';
+import {
+  globalBlockHeight,
+  watchBlockHeight,
+  blockHeightLagMs,
+  freshBlockHeight
+} from './utils/blockheight.mjs';
+import { fetchLandedSlot } from './utils/confirm.mjs';
+import { confirmPing, classifyTimeout, pingPayload } from './utils/pingcycle.mjs';
+import { fetchTransactionDetails } from './utils/txmeta.mjs';
+import {
+  buildPingTransaction,
+  estimatePingFee,
+  paddingBytes,
+  memoComputeUnits
+} from './utils/transaction.mjs';
+import { configureRunway, recordSpend } from './utils/runway.mjs';
+import { configureFeeBudget, recordFee, feeBudgetExhausted } from './utils/feebudget.mjs';
+import { loadWallets } from './utils/wallet.mjs';
+import { openSqlite, updatePing } from './utils/sqlite.mjs';
+import {
+  configureClickhouse,
+  runClickhouseSink
+} from './utils/clickhouse.mjs';
+import { addEventSink, setEventDefaults, emitPingEvent } from './utils/events.mjs';
+import { kafkaSink, runKafkaProducer } from './utils/kafka.mjs';
+import { natsSink, runNatsPublisher } from './utils/nats.mjs';
+import { watchSlot, watchSlotLag, freshSlot, slotSeenAt } from './utils/slot.mjs';
+import {
+  watchLeaderSchedule,
+  leaderForSlot,
+  slotsUntilLeaderRotation
+} from './utils/leaders.mjs';
+import { recordLeaderResult, logLeaderStats, configureLeaderMetrics } from './utils/leaderstats.mjs';
+import { recordPingSummary, logLatencySummary } from './utils/summary.mjs';
+import { configureLandingAlarm, recordLanding } from './utils/landingalarm.mjs';
+import { COMMITMENT_LEVELS, confirmationDeadlines } from './utils/deadline.mjs';
+import {
+  watchCommitmentLevels,
+  commitmentDeltas
+} from './utils/commitments.mjs';
+import { startResending, RESEND_BACKOFF_MODES } from './utils/resend.mjs';
+import { createSendConnections, sendToAll, endpointLabel } from './utils/fanout.mjs';
+import { createEndpointPool } from './utils/failover.mjs';
+import { supervise } from './utils/supervisor.mjs';
+import { watchBalance } from './utils/balance.mjs';
+import { watchMemory } from './utils/memory.mjs';
+import { startTrace, runTraceExporter } from './utils/tracing.mjs';
+import { setDefaultLabels } from './utils/metrics.mjs';
+import { runStatsdBackend } from './utils/statsd.mjs';
+import {
+  configureLogging,
+  setLogContext,
+  logEvent
+} from './utils/logger.mjs';
+import { globalPriorityFees, watchPriorityFees } from './utils/priorityfees.mjs';
+import { experimentFromEnv, experimentLabel } from './utils/experiment.mjs';
+import { globalRtt, watchRtt } from './utils/rtt.mjs';
+import { classifyPingError } from './utils/errors.mjs';
+import { rpcConnection } from './utils/rpcheaders.mjs';
+import { setupProxy } from './utils/proxy.mjs';
+import { priorityFeeProviderFromEnv } from './utils/feeproviders.mjs';
+import { runSystemdNotifier, sdNotifySync } from './utils/systemd.mjs';
+import {
+  loadConfig,
+  reloadConfig,
+  snapshotEnv,
+  scrubSetting,
+  describeSettings
+} from './utils/config.mjs';
+import { parseCli, runCommand } from './utils/cli.mjs';
+import { runProfiles } from './utils/profiles.mjs';
+import { installCrashHandlers, reportCrash } from './utils/crash.mjs';
+import { configureSentry, captureError } from './utils/sentry.mjs';
+import { configureNotifier, notify } from './utils/notifier.mjs';
+import { openVaQueue, watchVaQueue } from './utils/vaqueue.mjs';
+import { rateLimitedDelay, noteRateLimit } from './utils/ratelimit.mjs';
+import { parseWebhookHeaders } from './utils/webhook.mjs';
+import { runVaBatcher } from './utils/vabatch.mjs';
+import { createReporters, reportPing } from './utils/reporters.mjs';
+import { addRoute, startServer, jsonResponse } from './utils/server.mjs';
+import { addHealthRoutes, pingState } from './utils/health.mjs';
+import { addPingRoutes, recordRecentPing } from './utils/pingsapi.mjs';
+import { addDashboardRoute } from './utils/dashboard.mjs';
+import {
+  addMetricsRoute,
+  feesPaidLamportsTotal,
+  pingErrorsTotal,
+  pingsTotal,
+  transactionsSentTotal,
+  resendsTotal,
+  confirmationTimeoutsTotal,
+  lateLandingsTotal,
+  failedTransactionsTotal,
+  vaSubmissionErrorsTotal,
+  sendLatencyMs
+} from './utils/pingmetrics.mjs';
+import {
+  trackTimedOutSignature,
+  watchLateLandings
+} from './utils/latelanding.mjs';
 
 // Catch interrupts & exit
-process.on(
-  'SIGINT',
-  function() {
-    console.log(`${new Date().toISOString()} Caught interrupt signal`, '\n');
-    process.exit();
-  }
-);
+['SIGINT', 'SIGTERM'].forEach((signal) => {
+  process.on(
+    signal,
+    function() {
+      console.log(`${new Date().toISOString()} Caught ${signal} signal`, '\n');
+      sdNotifySync('STOPPING=1');
+      process.exit();
+    }
+  );
+});
 
-// Read constants from .env
+// Settings come from command line flags, then .env, then the optional
+// --config file
+const cli = parseCli(process.argv.slice(2));
+snapshotEnv();
 dotenv.config();
+const CONFIG_FILE = cli.config || process.env.CONFIG_FILE;
+try {
+  loadConfig(CONFIG_FILE, cli.checkRequired);
+  // Through PROXY_URL or HTTPS_PROXY / HTTP_PROXY, if set
+  setupProxy(process.env);
+} catch (e) {
+  console.log(`${new Date().toISOString()} ERROR: ${e.message}`);
+  process.exit(1);
+}
+// Commands other than run do their thing and exit
+if (cli.command !== 'run') process.exit(await runCommand(cli));
+// With CLUSTER_PROFILES, run each profile as a child process instead of
+// pinging from this one
+if (process.env.CLUSTER_PROFILES) {
+  try {
+    await runProfiles(process.env.CLUSTER_PROFILES.split(',').map((f) => f.trim()).filter((f) => f));
+  } catch (e) {
+    console.log(`${new Date().toISOString()} ERROR: ${e.message}`);
+    process.exit(1);
+  }
+}
+// text (default) or json, one JSON object per line
+const LOG_FORMAT = process.env.LOG_FORMAT || 'text';
+// Optionally also log to LOG_FILE, rotated at LOG_FILE_MAX_MB and/or daily
+// (LOG_FILE_ROTATE_DAILY=true), keeping LOG_FILE_MAX_FILES old files
+const LOG_FILE = process.env.LOG_FILE;
+const LOG_FILE_MAX_MB = Number(process.env.LOG_FILE_MAX_MB || 100);
+const LOG_FILE_MAX_FILES = Number(process.env.LOG_FILE_MAX_FILES || 5);
+const LOG_FILE_ROTATE_DAILY = process.env.LOG_FILE_ROTATE_DAILY === 'true' ? true : false;
+configureLogging({
+  format: LOG_FORMAT,
+  file: LOG_FILE,
+  maxSizeBytes: LOG_FILE_MAX_MB * 1024 * 1024,
+  maxFiles: LOG_FILE_MAX_FILES,
+  rotateDaily: LOG_FILE_ROTATE_DAILY
+});
+// Identifies this pinger in alerts and metrics
+const PINGER_NAME = process.env.PINGER_NAME || os.hostname();
+// Where this pinger runs, e.g. fra or nyc
+const PINGER_REGION = process.env.PINGER_REGION;
+// Optional sinks that are told about crashes
+const CRASH_WEBHOOK_URL = process.env.CRASH_WEBHOOK_URL;
+const PUSHGATEWAY_URL = process.env.PUSHGATEWAY_URL;
+installCrashHandlers({
+  webhookUrl: CRASH_WEBHOOK_URL,
+  pushgatewayUrl: PUSHGATEWAY_URL,
+  pingerName: PINGER_NAME
+});
 const RPC_ENDPOINT = process.env.RPC_ENDPOINT;
-const USER_KEYPAIR = web3.Keypair.fromSecretKey(
-  bs58.decode(process.env.WALLET_PRIVATE_KEYPAIR)
-);
-const SLEEP_MS = process.env.SLEEP_MS;
+// Optional comma separated list of endpoints to send every TX through.
+// Defaults to RPC_ENDPOINT.
+const RPC_ENDPOINTS = process.env.RPC_ENDPOINTS
+  ? process.env.RPC_ENDPOINTS.split(',').map((e) => e.trim()).filter((e) => e)
+  : [RPC_ENDPOINT];
+// Optional RPC provider names, e.g. triton, helius or self-hosted, one per
+// RPC_ENDPOINTS entry (or for RPC_ENDPOINT). Sends, send latencies and send
+// errors are tagged with them. Defaults to each endpoint's host.
+const RPC_PROVIDERS = (process.env.RPC_PROVIDERS || '').split(',').map((p) => p.trim());
+// Optional region of each RPC_ENDPOINTS entry, e.g. fra or nyc, to compare
+// submission paths from one host. Results record the region of the endpoint
+// that accepted the TX first.
+const RPC_REGIONS = (process.env.RPC_REGIONS || '').split(',').map((r) => r.trim());
+// Optional Sentry DSN to report errors to
+const SENTRY_DSN = process.env.SENTRY_DSN;
+if (SENTRY_DSN) {
+  configureSentry(SENTRY_DSN, {
+    pinger_name: PINGER_NAME,
+    region: PINGER_REGION,
+    endpoint: RPC_ENDPOINT ? endpointLabel(RPC_ENDPOINT) : undefined,
+    provider: RPC_PROVIDERS[0] || undefined
+  });
+}
+// Optional Slack/Discord incoming webhooks and Telegram bot for alerts
+// (timeout streaks, watcher restarts, low balance, VA submission failures).
+// Each kind of alert is sent at most once per ALERT_MIN_INTERVAL_MS.
+const SLACK_WEBHOOK_URL = process.env.SLACK_WEBHOOK_URL;
+const DISCORD_WEBHOOK_URL = process.env.DISCORD_WEBHOOK_URL;
+const TELEGRAM_BOT_TOKEN = process.env.TELEGRAM_BOT_TOKEN;
+const TELEGRAM_CHAT_ID = process.env.TELEGRAM_CHAT_ID;
+const ALERT_MIN_INTERVAL_MS = Number(process.env.ALERT_MIN_INTERVAL_MS || 900000);
+configureNotifier({
+  channels: [
+    { type: 'slack', url: SLACK_WEBHOOK_URL },
+    { type: 'discord', url: DISCORD_WEBHOOK_URL },
+    {
+      type: 'telegram',
+      url: TELEGRAM_BOT_TOKEN && TELEGRAM_CHAT_ID
+        ? `https://api.telegram.org/bot${TELEGRAM_BOT_TOKEN}/sendMessage`
+        : undefined,
+      chatId: TELEGRAM_CHAT_ID
+    }
+  ].filter((c) => c.url),
+  pingerName: PINGER_NAME,
+  minIntervalMs: ALERT_MIN_INTERVAL_MS
+});
+// fanout sends through every RPC_ENDPOINTS entry at once. failover treats
+// the list as a priority order and sends through one endpoint at a time.
+// rotate sends each ping through the next endpoint in turn.
+const RPC_SEND_MODE = process.env.RPC_SEND_MODE || 'fanout';
+if (!['fanout', 'failover', 'rotate'].includes(RPC_SEND_MODE)) {
+  throw new Error('RPC_SEND_MODE must be one of fanout, failover, rotate');
+}
+const RPC_FAILOVER_THRESHOLD = process.env.RPC_FAILOVER_THRESHOLD || 3;
+const RPC_FAILOVER_RESET_MS = process.env.RPC_FAILOVER_RESET_MS || 60000;
+// The wallets come from the WALLET_KEYPAIR_PATHS files, the
+// WALLET_KEYPAIR_PATH file or, failing that, WALLET_PRIVATE_KEYPAIR in base58.
+// An encrypted file prompts for its passphrase unless WALLET_PASSPHRASE_FILE
+// is set. Several wallets are used round-robin so consecutive pings don't
+// contend for the same account's write lock.
+const WALLETS = await loadWallets(process.env);
+let walletIndex = 0;
+scrubSetting('WALLET_PRIVATE_KEYPAIR');
 const VA_API_KEY = process.env.VA_API_KEY;
-// process.env.VERBOSE_LOG returns a string. e.g. 'true'
-const VERBOSE_LOG = process.env.VERBOSE_LOG === 'true' ? true : false;
+// Which validators.app instance and cluster to report to. VA_ENDPOINT can point
+// at a staging instance.
+const VA_ENDPOINT = (process.env.VA_ENDPOINT || 'https://www.validators.app').replace(/\/$/, '');
+const VA_CLUSTER = process.env.VA_CLUSTER || 'mainnet';
+if (!['mainnet', 'testnet', 'devnet'].includes(VA_CLUSTER)) {
+  throw new Error('VA_CLUSTER must be one of mainnet, testnet, devnet');
+}
+// On devnet and testnet, airdrop AIRDROP_LAMPORTS to wallets whose balance
+// drops below AIRDROP_BELOW_LAMPORTS. Unset disables top ups.
+const AIRDROP_BELOW_LAMPORTS = Number(process.env.AIRDROP_BELOW_LAMPORTS || 0);
+const AIRDROP_LAMPORTS = Number(process.env.AIRDROP_LAMPORTS || 1000000000);
+if (AIRDROP_BELOW_LAMPORTS && VA_CLUSTER === 'mainnet') {
+  throw new Error('AIRDROP_BELOW_LAMPORTS only works on devnet and testnet (see VA_CLUSTER)');
+}
+const BLOCK_HEIGHT_POLL_MS = process.env.BLOCK_HEIGHT_POLL_MS || 1000;
+const SIGNATURE_STATUS_POLL_MS = process.env.SIGNATURE_STATUS_POLL_MS || 2000;
+// Record processed, confirmed & finalized times for every TX. Reports wait
+// for finalization (up to FINALIZED_TIMEOUT_MS) when this is on.
+const MEASURE_ALL_COMMITMENTS = process.env.MEASURE_ALL_COMMITMENTS === 'true' ? true : false;
+// Cached values older than these are treated as unknown
+const BLOCK_HEIGHT_MAX_AGE_MS = process.env.BLOCK_HEIGHT_MAX_AGE_MS || 5000;
+const SLOT_MAX_AGE_MS = process.env.SLOT_MAX_AGE_MS || 2000;
+// Add a priority fee at this percentile of recent prioritization fees.
+// Unset sends pings without a priority fee.
+const PRIORITY_FEE_PERCENTILE = process.env.PRIORITY_FEE_PERCENTILE;
+const PRIORITY_FEE_POLL_MS = process.env.PRIORITY_FEE_POLL_MS || 1000;
+// Where the fee comes from, see utils/feeproviders.mjs. rpc (the default)
+// uses PRIORITY_FEE_PERCENTILE of getRecentPrioritizationFees, optionally
+// over a sliding window of PRIORITY_FEE_WINDOW_SLOTS slots. fixed pays
+// PRIORITY_FEE_MICRO_LAMPORTS. helius asks getPriorityFeeEstimate on
+// PRIORITY_FEE_API_URL (default RPC_ENDPOINT) for the ping's accounts at
+// PRIORITY_FEE_LEVEL. triton asks PRIORITY_FEE_API_URL (default
+// RPC_ENDPOINT) for the PRIORITY_FEE_PERCENTILE within each slot. jito pays
+// the PRIORITY_FEE_PERCENTILE bundle tip floor as a priority fee. target
+// picks the cheapest fee that our PING_SQLITE_DB history says lands within
+// PRIORITY_FEE_TARGET_SLOTS slots with PRIORITY_FEE_TARGET_PROBABILITY,
+// falling back to rpc until it has PRIORITY_FEE_TARGET_MIN_SAMPLES pings
+// at some fee. Sources other than rpc always add a priority fee. Fees are looked up for the
+// wallets plus any PRIORITY_FEE_ACCOUNTS. PRIORITY_FEE_EMA_ALPHA smooths the
+// fee with a moving average, clamping spikes to PRIORITY_FEE_SPIKE_FACTOR
+// times the average. PRIORITY_FEE_MIN and PRIORITY_FEE_MAX bound the result.
+const PRIORITY_FEE_PROVIDER = priorityFeeProviderFromEnv(process.env, [
+  ...WALLETS.map(({ keypair }) => keypair.publicKey.toBase58()),
+  web3.SystemProgram.programId.toBase58()
+]);
+// Compute unit limit set on pings that carry a priority fee
+const COMPUTE_UNIT_LIMIT = process.env.COMPUTE_UNIT_LIMIT || 1000;
+// Step pings through every combination of the CU limits in
+// EXPERIMENT_CU_LIMITS, the CU prices (micro-lamports) in
+// EXPERIMENT_CU_PRICES and the TX sizes (bytes, padded with a memo) in
+// EXPERIMENT_TX_SIZES, confirming each at the next of EXPERIMENT_COMMITMENTS.
+// A swept price replaces the priority fee source and a swept commitment
+// replaces COMMITMENT_LEVEL. Or run the named variants in EXPERIMENT_FILE,
+// see ping-thing.experiment.toml.
+const EXPERIMENT = experimentFromEnv(process.env);
+// Check the wallet balance every BALANCE_POLL_MS and warn below
+// LOW_BALANCE_LAMPORTS
+const BALANCE_POLL_MS = process.env.BALANCE_POLL_MS || 60000;
+const LOW_BALANCE_LAMPORTS = Number(process.env.LOW_BALANCE_LAMPORTS || 10000000);
+// Estimate each wallet's runway from its spend over RUNWAY_WINDOW_MS and warn
+// when it drops below RUNWAY_ALERT_HOURS. Unset disables the warning.
+const RUNWAY_WINDOW_MS = Number(process.env.RUNWAY_WINDOW_MS || 3600000);
+const RUNWAY_ALERT_HOURS = Number(process.env.RUNWAY_ALERT_HOURS || 0);
+// Pause pinging until 00:00 UTC once the day's fees reach this. Unset
+// disables the cap.
+const MAX_DAILY_FEE_LAMPORTS = Number(process.env.MAX_DAILY_FEE_LAMPORTS || 0);
+// Keep the day's fee spend in this file so MAX_DAILY_FEE_LAMPORTS holds
+// across restarts
+const FEE_BUDGET_FILE = process.env.FEE_BUDGET_FILE;
+// Exit (to be restarted by systemd) if RSS grows past this many MB. Unset
+// disables the check.
+const MAX_RSS_MB = process.env.MAX_RSS_MB;
+// Export a trace of every ping to this OTLP/HTTP endpoint, e.g.
+// http://localhost:4318. Unset disables tracing.
+const OTLP_ENDPOINT = process.env.OTLP_ENDPOINT;
+// prometheus (default) only serves /metrics. statsd or dogstatsd also sends
+// every metric to STATSD_ADDRESS (host:port).
+const METRICS_BACKEND = process.env.METRICS_BACKEND || 'prometheus';
+if (!['prometheus', 'statsd', 'dogstatsd'].includes(METRICS_BACKEND)) {
+  throw new Error('METRICS_BACKEND must be one of prometheus, statsd, dogstatsd');
+}
+const STATSD_ADDRESS = process.env.STATSD_ADDRESS || '127.0.0.1:8125';
+// Where each ping goes, e.g. validators_app,prometheus,sqlite. Unset uses
+// validators_app, prometheus and every sink configured below, see
+// utils/reporters.mjs.
+const REPORTERS = (process.env.REPORTERS || '').split(',').map((r) => r.trim()).filter((r) => r);
+// Also write every ping to InfluxDB. INFLUXDB_WRITE_URL is the full write
+// endpoint including org/bucket (v2) or db (v1) and precision=ms.
+const INFLUXDB_WRITE_URL = process.env.INFLUXDB_WRITE_URL;
+const INFLUXDB_TOKEN = process.env.INFLUXDB_TOKEN;
+// Optionally POST every ping result to PING_WEBHOOK_URL. PING_WEBHOOK_HEADERS
+// is a JSON object of extra headers and PING_WEBHOOK_TEMPLATE an optional body
+// template, see utils/webhook.mjs.
+const PING_WEBHOOK_URL = process.env.PING_WEBHOOK_URL;
+const PING_WEBHOOK_HEADERS = parseWebhookHeaders(process.env.PING_WEBHOOK_HEADERS || '{}');
+const PING_WEBHOOK_TEMPLATE = process.env.PING_WEBHOOK_TEMPLATE;
+// Append every ping, including fields VA doesn't take, to this JSONL file
+const PING_LOG_FILE = process.env.PING_LOG_FILE;
+// Write pings to a CSV file per day in this directory
+const PING_CSV_DIR = process.env.PING_CSV_DIR;
+// Record pings in this SQLite database (needs the sqlite3 CLI)
+const PING_SQLITE_DB = process.env.PING_SQLITE_DB;
+if (PING_SQLITE_DB) openSqlite(PING_SQLITE_DB);
+// Insert pings into CLICKHOUSE_TABLE through the ClickHouse HTTP interface at
+// CLICKHOUSE_URL, e.g. http://localhost:8123, in batches of up to
+// CLICKHOUSE_BATCH_SIZE every CLICKHOUSE_INTERVAL_MS
+const CLICKHOUSE_URL = process.env.CLICKHOUSE_URL;
+const CLICKHOUSE_TABLE = process.env.CLICKHOUSE_TABLE || 'ping_thing.pings';
+const CLICKHOUSE_USER = process.env.CLICKHOUSE_USER;
+const CLICKHOUSE_PASSWORD = process.env.CLICKHOUSE_PASSWORD;
+const CLICKHOUSE_INTERVAL_MS = Number(process.env.CLICKHOUSE_INTERVAL_MS || 10000);
+const CLICKHOUSE_BATCH_SIZE = Number(process.env.CLICKHOUSE_BATCH_SIZE || 1000);
+// Publish ping lifecycle events to KAFKA_TOPIC through the Kafka REST Proxy at
+// KAFKA_REST_URL, batched every KAFKA_INTERVAL_MS
+const KAFKA_REST_URL = process.env.KAFKA_REST_URL;
+const KAFKA_TOPIC = process.env.KAFKA_TOPIC || 'ping-thing';
+const KAFKA_INTERVAL_MS = Number(process.env.KAFKA_INTERVAL_MS || 1000);
+// Publish ping lifecycle events to NATS_URL (nats://host:4222) on
+// <NATS_SUBJECT_PREFIX>.<event type>
+const NATS_URL = process.env.NATS_URL;
+const NATS_SUBJECT_PREFIX = process.env.NATS_SUBJECT_PREFIX || 'ping_thing';
+// Port for the dashboard, /metrics, /healthz, /readyz & /pings server. Unset
+// disables it. Use STATUS_BIND_ADDRESS=0.0.0.0 to expose it outside the host, e.g. in
+// a container.
+const STATUS_PORT = process.env.STATUS_PORT;
+const STATUS_BIND_ADDRESS = process.env.STATUS_BIND_ADDRESS || '127.0.0.1';
+// How many pings /pings/recent and /pings/stats keep in memory
+const RECENT_PINGS_MAX = Number(process.env.RECENT_PINGS_MAX || 1000);
+// Resubscribe to slot updates after this long without one
+const SLOT_STALE_MS = process.env.SLOT_STALE_MS || 5000;
+// How often to measure how far the slot subscription is behind getSlot
+const SLOT_LAG_CHECK_MS = process.env.SLOT_LAG_CHECK_MS || 10000;
+// How often to measure the baseline round-trip time to RPC_ENDPOINT
+const RTT_POLL_MS = process.env.RTT_POLL_MS || 30000;
+// How often to check for a new epoch's leader schedule
+const LEADER_SCHEDULE_POLL_MS = process.env.LEADER_SCHEDULE_POLL_MS || 60000;
+// Per-leader stats cover the last LEADER_STATS_WINDOW pings to each leader and
+// the worst LEADER_STATS_LOG_COUNT are logged every LEADER_STATS_LOG_MS.
+const LEADER_STATS_WINDOW = process.env.LEADER_STATS_WINDOW || 100;
+const LEADER_STATS_LOG_MS = process.env.LEADER_STATS_LOG_MS || 3600000;
+const LEADER_STATS_LOG_COUNT = process.env.LEADER_STATS_LOG_COUNT || 10;
+// Export per-leader metrics for the LEADER_STATS_METRICS_COUNT leaders with
+// the most pings
+const LEADER_STATS_METRICS_COUNT = Number(process.env.LEADER_STATS_METRICS_COUNT || 20);
+// How often to log latency quantiles and the landing rate since the last
+// summary
+const SUMMARY_INTERVAL_MS = process.env.SUMMARY_INTERVAL_MS || 600000;
+// Failed VA submissions are retried every VA_QUEUE_RETRY_MS, with backoff
+// while VA is down. Set VA_QUEUE_FILE to keep the queue across restarts. At
+// most VA_QUEUE_MAX submissions are kept.
+const VA_QUEUE_FILE = process.env.VA_QUEUE_FILE;
+const VA_QUEUE_MAX = Number(process.env.VA_QUEUE_MAX || 10000);
+const VA_QUEUE_RETRY_MS = Number(process.env.VA_QUEUE_RETRY_MS || 30000);
+// Submit ping results to VA in batches every VA_BATCH_INTERVAL_MS, or once
+// VA_BATCH_MAX_SIZE are waiting, instead of one POST per ping
+const VA_BATCH_INTERVAL_MS = process.env.VA_BATCH_INTERVAL_MS;
+const VA_BATCH_MAX_SIZE = Number(process.env.VA_BATCH_MAX_SIZE || 100);
+// Look up compute units consumed, the fee paid and the index in the block of
+// each landed ping. Costs a getTransaction and a getBlock per ping. The fee
+// paid then drives the runway and MAX_DAILY_FEE_LAMPORTS instead of an
+// estimate.
+const FETCH_TX_DETAILS = process.env.FETCH_TX_DETAILS === 'true' ? true : false;
+// Alert when fewer than LANDING_RATE_ALERT_THRESHOLD (a fraction) of the last
+// LANDING_RATE_WINDOW pings confirmed in time. Alerts are logged, exported and
+// optionally posted to LANDING_RATE_ALERT_WEBHOOK_URL.
+const LANDING_RATE_WINDOW = Number(process.env.LANDING_RATE_WINDOW || 50);
+const LANDING_RATE_ALERT_THRESHOLD = process.env.LANDING_RATE_ALERT_THRESHOLD;
+const LANDING_RATE_ALERT_WEBHOOK_URL = process.env.LANDING_RATE_ALERT_WEBHOOK_URL;
+
+// One of processed, confirmed or finalized
+const COMMITMENT_LEVEL = process.env.COMMITMENT_LEVEL || 'confirmed';
+if (!COMMITMENT_LEVELS.includes(COMMITMENT_LEVEL)) {
+  throw new Error(`COMMITMENT_LEVEL must be one of ${COMMITMENT_LEVELS.join(', ')}`);
+}
+
+// Settings that SIGHUP or POST /reload can change without a restart. The rest
+// need one.
+let SLEEP_MS;
+let VERBOSE_LOG;
+let LATE_LANDING_GRACE_MS;
+let RESEND_INTERVAL_MS;
+let MAX_RESENDS;
+let RESEND_BACKOFF;
+let FINALIZED_TIMEOUT_MS;
+let ALERT_TIMEOUT_STREAK;
+let REPORT_FAILURES;
+let CONFIRMATION_DEADLINES;
+function readReloadableSettings() {
+  // One of fixed, exponential or jittered
+  const resendBackoff = process.env.RESEND_BACKOFF || 'fixed';
+  if (!RESEND_BACKOFF_MODES.includes(resendBackoff)) {
+    throw new Error(`RESEND_BACKOFF must be one of ${RESEND_BACKOFF_MODES.join(', ')}`);
+  }
+  RESEND_BACKOFF = resendBackoff;
+  SLEEP_MS = process.env.SLEEP_MS;
+  // process.env.VERBOSE_LOG returns a string. e.g. 'true'
+  VERBOSE_LOG = process.env.VERBOSE_LOG === 'true' ? true : false;
+  // How long to keep watching a timed out TX in case it lands late
+  LATE_LANDING_GRACE_MS = process.env.LATE_LANDING_GRACE_MS || 300000;
+  // Resend the TX while waiting for confirmation. MAX_RESENDS=0 disables it.
+  RESEND_INTERVAL_MS = process.env.RESEND_INTERVAL_MS || 2000;
+  MAX_RESENDS = Number(process.env.MAX_RESENDS || 0);
+  FINALIZED_TIMEOUT_MS = process.env.FINALIZED_TIMEOUT_MS || 60000;
+  // Alert after this many timeouts in a row
+  ALERT_TIMEOUT_STREAK = Number(process.env.ALERT_TIMEOUT_STREAK || 5);
+  // Also send TXs that landed but failed on chain to VA. Timeouts are always sent.
+  REPORT_FAILURES = process.env.REPORT_FAILURES === 'true' ? true : false;
+  CONFIRMATION_DEADLINES = confirmationDeadlines(process.env);
+  // Switching the priority fee on or off needs a restart, the percentile
+  // doesn't
+  if (PRIORITY_FEE_PERCENTILE && process.env.PRIORITY_FEE_PERCENTILE) {
+    globalPriorityFees.percentile = Number(process.env.PRIORITY_FEE_PERCENTILE);
+  }
+}
+readReloadableSettings();
+
+// Re-read .env and the config file and apply the reloadable settings. A bad
+// config is logged and leaves the current settings in place.
+function reload() {
+  try {
+    reloadConfig(CONFIG_FILE);
+    readReloadableSettings();
+    console.log(`${new Date().toISOString()} Reloaded settings`);
+    return true;
+  } catch (e) {
+    console.log(`${new Date().toISOString()} ERROR: Reload failed, keeping the current settings: ${e.message}`);
+    return false;
+  }
+}
+process.on('SIGHUP', reload);
 
 // Set up web3 client
 // const walletAccount = new web3.PublicKey(USER_KEYPAIR.publicKey);
-const commitmentLevel = 'confirmed';
-const connection = new web3.Connection(RPC_ENDPOINT, commitmentLevel);
-
-// Set up our REST client
-const restClient = new XMLHttpRequest();
-
-// Setup our transaction
-const tx = new web3.Transaction();
-tx.add(
-  web3.SystemProgram.transfer({
-    fromPubkey: USER_KEYPAIR.publicKey,
-    toPubkey: USER_KEYPAIR.publicKey,
-    lamports: 5000
+const commitmentLevel = COMMITMENT_LEVEL;
+// 429s surface so the polling can back off, see utils/ratelimit.mjs
+const connection = rpcConnection(RPC_ENDPOINT, {
+  commitment: commitmentLevel,
+  disableRetryOnRateLimit: true
+});
+const sendConnections = createSendConnections(
+  RPC_ENDPOINTS,
+  // Let 429s surface so failover can react to them
+  RPC_SEND_MODE === 'failover'
+    ? { commitment: commitmentLevel, disableRetryOnRateLimit: true }
+    : commitmentLevel,
+  { providers: RPC_PROVIDERS, regions: RPC_REGIONS }
+);
+const endpointPool = createEndpointPool(sendConnections, {
+  failureThreshold: RPC_FAILOVER_THRESHOLD,
+  resetMs: RPC_FAILOVER_RESET_MS
+});
+// targets are the send connections for this ping, see pingSendConnections
+const sendTransaction = (rawTransaction, options, targets) => (
+  RPC_SEND_MODE === 'failover'
+    ? endpointPool.send(rawTransaction, options)
+    : sendToAll(targets, rawTransaction, options)
+);
+let rotateIndex = 0;
+// rotate mode uses one endpoint per ping, for the send and any resends
+const pingSendConnections = () => (
+  RPC_SEND_MODE === 'rotate'
+    ? [sendConnections[rotateIndex++ % sendConnections.length]]
+    : sendConnections
+);
+// The send connections an experiment variant's send_via picks
+const variantSendConnections = (sendVia) =>
+  sendConnections.filter(({ label, provider, region }) => [label, provider, region].includes(sendVia));
+(EXPERIMENT ? EXPERIMENT.cells : []).forEach(({ variant, send_via: sendVia }) => {
+  if (!sendVia) return;
+  if (RPC_SEND_MODE === 'failover') {
+    throw new Error(`Variant ${variant} sets send_via, which RPC_SEND_MODE=failover doesn't support`);
+  }
+  if (!variantSendConnections(sendVia).length) {
+    throw new Error(`Variant ${variant} send_via ${sendVia} matches no RPC_ENDPOINTS label, provider or region`);
+  }
+});
+
+// Send and record the round-trip time of every endpoint call, as a metric and
+// as a span under parentSpan. kind is send or resend.
+async function sendAndTime(rawTransaction, options, kind, parentSpan, targets) {
+  const span = parentSpan.child(kind);
+  let results = [];
+  try {
+    results = await sendTransaction(rawTransaction, options, targets);
+    span.end();
+    return results;
+  } catch (e) {
+    results = e.sendResults || [];
+    span.end(e);
+    throw e;
+  } finally {
+    results.forEach(({ label, provider, region, ms, error }) => {
+      sendLatencyMs.observe(
+        { endpoint: label, provider: provider, region: region || '', kind: kind, outcome: error ? 'error' : 'ok' },
+        ms
+      );
+    });
+  }
+}
+
+// Background watchers are restarted by the supervisor if they die.
+// Watch the block height
+supervise('block_height', () =>
+  watchBlockHeight(connection, commitmentLevel, BLOCK_HEIGHT_POLL_MS)
+);
+
+// Follow the current slot & leader schedule
+supervise('slot', () => watchSlot(connection, SLOT_STALE_MS));
+supervise('slot_lag', () => watchSlotLag(connection, SLOT_LAG_CHECK_MS));
+supervise('rtt', () => watchRtt(connection, RTT_POLL_MS));
+supervise('leader_schedule', () =>
+  watchLeaderSchedule(connection, LEADER_SCHEDULE_POLL_MS)
+);
+configureLeaderMetrics(LEADER_STATS_METRICS_COUNT);
+supervise('leader_stats', () =>
+  logLeaderStats(LEADER_STATS_LOG_MS, LEADER_STATS_LOG_COUNT)
+);
+supervise('summary', () => logLatencySummary(SUMMARY_INTERVAL_MS));
+if (LANDING_RATE_ALERT_THRESHOLD) {
+  configureLandingAlarm({
+    window: LANDING_RATE_WINDOW,
+    threshold: Number(LANDING_RATE_ALERT_THRESHOLD),
+    webhookUrl: LANDING_RATE_ALERT_WEBHOOK_URL,
+    pingerName: PINGER_NAME
+  });
+}
+
+// Follow recent priority fees
+if (PRIORITY_FEE_PROVIDER) {
+  supervise('priority_fees', () =>
+    watchPriorityFees(PRIORITY_FEE_PROVIDER, PRIORITY_FEE_PERCENTILE, PRIORITY_FEE_POLL_MS)
+  );
+}
+
+// Keep an eye on the wallet balance
+configureRunway({ windowMs: RUNWAY_WINDOW_MS, alertHours: RUNWAY_ALERT_HOURS });
+configureFeeBudget(MAX_DAILY_FEE_LAMPORTS, FEE_BUDGET_FILE);
+supervise('balance', () =>
+  watchBalance(
+    connection,
+    WALLETS,
+    BALANCE_POLL_MS,
+    LOW_BALANCE_LAMPORTS,
+    AIRDROP_BELOW_LAMPORTS
+      ? { belowLamports: AIRDROP_BELOW_LAMPORTS, lamports: AIRDROP_LAMPORTS }
+      : undefined
+  )
+);
+
+// Restart before we run out of memory
+if (MAX_RSS_MB) {
+  supervise('memory', () => watchMemory(Number(MAX_RSS_MB), 30000));
+}
+
+// Watch timed out TXs for late landings
+supervise('late_landing', () =>
+  watchLateLandings(connection, commitmentLevel, SIGNATURE_STATUS_POLL_MS, (signature, { outcome, slot, ms }) => {
+    lateLandingsTotal.inc({ outcome });
+    emitPingEvent(outcome === 'landed' ? 'late_landed' : 'dropped', signature, {
+      slot_landed: slot,
+      time_ms: ms
+    });
+    if (PING_SQLITE_DB) {
+      updatePing(PING_SQLITE_DB, signature, {
+        late_outcome: outcome,
+        late_landing_ms: ms,
+        late_slot_landed: slot
+      });
+    }
   })
 );
 
-if (VERBOSE_LOG) console.log(`${new Date().toISOString()} Starting script`);
+// Every metric series carries the pinger name
+setDefaultLabels({ pinger_name: PINGER_NAME });
+if (METRICS_BACKEND !== 'prometheus') {
+  supervise('statsd', () =>
+    runStatsdBackend(METRICS_BACKEND, STATSD_ADDRESS, 10000)
+  );
+}
+
+// Serve the dashboard, /metrics, /healthz, /readyz and /pings when a port is
+// configured
+if (STATUS_PORT) {
+  addDashboardRoute();
+  addMetricsRoute();
+  addHealthRoutes({
+    slotMaxAgeMs: SLOT_MAX_AGE_MS,
+    blockHeightMaxAgeMs: BLOCK_HEIGHT_MAX_AGE_MS
+  });
+  addPingRoutes(RECENT_PINGS_MAX);
+  addRoute('/reload', () => {
+    const ok = reload();
+    return jsonResponse(ok ? 200 : 400, { reloaded: ok });
+  }, 'POST');
+  startServer(STATUS_PORT, STATUS_BIND_ADDRESS);
+}
+
+// Export ping traces
+if (OTLP_ENDPOINT) {
+  supervise('trace_exporter', () =>
+    runTraceExporter(OTLP_ENDPOINT, PINGER_NAME, 5000)
+  );
+}
+
+// Tell systemd (when run as Type=notify) that we're ready once the watchers
+// have data, and keep the watchdog fed while that data stays fresh
+runSystemdNotifier(
+  () => freshSlot(SLOT_MAX_AGE_MS) !== undefined &&
+    freshBlockHeight(BLOCK_HEIGHT_MAX_AGE_MS) !== undefined,
+  () => freshSlot(SLOT_STALE_MS) !== undefined &&
+    freshBlockHeight(BLOCK_HEIGHT_MAX_AGE_MS) !== undefined
+);
+
+const VA_PING_THING_URL = `${VA_ENDPOINT}/api/v1/ping-thing/${VA_CLUSTER}`;
+const VA_PING_THING_BATCH_URL = `${VA_ENDPOINT}/api/v1/ping-thing-batch/${VA_CLUSTER}`;
+
+// Submissions that fail with a network error, 429 or 5xx are queued and
+// retried in the background
+openVaQueue(VA_QUEUE_FILE, VA_QUEUE_MAX);
+supervise('va_queue', () =>
+  watchVaQueue(VA_PING_THING_URL, VA_API_KEY, VA_QUEUE_RETRY_MS)
+);
+if (CLICKHOUSE_URL) {
+  configureClickhouse({
+    url: CLICKHOUSE_URL,
+    table: CLICKHOUSE_TABLE,
+    user: CLICKHOUSE_USER,
+    password: CLICKHOUSE_PASSWORD,
+    maxBuffered: CLICKHOUSE_BATCH_SIZE * 10
+  });
+  supervise('clickhouse', () =>
+    runClickhouseSink(CLICKHOUSE_INTERVAL_MS, CLICKHOUSE_BATCH_SIZE)
+  );
+}
+setEventDefaults({ pinger_name: PINGER_NAME, region: PINGER_REGION });
+if (KAFKA_REST_URL) {
+  addEventSink(kafkaSink(100000));
+  supervise('kafka', () =>
+    runKafkaProducer(KAFKA_REST_URL, KAFKA_TOPIC, KAFKA_INTERVAL_MS)
+  );
+}
+if (NATS_URL) {
+  addEventSink(natsSink(NATS_SUBJECT_PREFIX));
+  supervise('nats', () => runNatsPublisher(NATS_URL, PINGER_NAME));
+}
+if (VA_BATCH_INTERVAL_MS) {
+  supervise('va_batch', () =>
+    runVaBatcher(
+      VA_PING_THING_BATCH_URL,
+      VA_API_KEY,
+      Number(VA_BATCH_INTERVAL_MS),
+      (error) => {
+        vaSubmissionErrorsTotal.inc();
+        captureError(error, 'va_submission');
+        notify('va_submission', error.message);
+      }
+    )
+  );
+}
+const reporters = createReporters(REPORTERS, process.env, {
+  va: {
+    url: VA_PING_THING_URL,
+    apiKey: VA_API_KEY,
+    batchIntervalMs: VA_BATCH_INTERVAL_MS,
+    batchMaxSize: VA_BATCH_MAX_SIZE
+  },
+  pingLogFile: PING_LOG_FILE,
+  pingCsvDir: PING_CSV_DIR,
+  pingSqliteDb: PING_SQLITE_DB,
+  influx: { writeUrl: INFLUXDB_WRITE_URL, token: INFLUXDB_TOKEN },
+  webhook: { url: PING_WEBHOOK_URL, headers: PING_WEBHOOK_HEADERS, template: PING_WEBHOOK_TEMPLATE },
+  pingerName: PINGER_NAME,
+  region: PINGER_REGION
+});
+
+if (VERBOSE_LOG) {
+  console.log(`${new Date().toISOString()} Starting script with settings:\n${describeSettings(process.env)}`);
+}
 
 // Run inside a loop that will exit after 3 consecutive failures
 let tryCount = 0;
 const maxTries = 3;
 
 // Pre-define loop constants & variables
-let signature = undefined;
 let txSuccess = undefined;
+let failureCategory = undefined;
+let errorCategory = undefined;
+let failureMessage = undefined;
+let pingSpan = undefined;
+let consecutiveTimeouts = 0;
 const uninterrupted = true;
 
 // Loop until interrupted
 while( uninterrupted ) {
+  // Sit out the rest of the day once the fee budget is spent
+  if (feeBudgetExhausted()) {
+    await new Promise(r => setTimeout(r, SLEEP_MS));
+    continue;
+  }
+
   // reset these on each loop:
-  signature = undefined;
   txSuccess = undefined;
+  failureCategory = undefined;
+  errorCategory = undefined;
+  failureMessage = undefined;
+  pingSpan = startTrace('ping', {
+    'pinger.name': PINGER_NAME,
+    'commitment': commitmentLevel
+  });
   try {
+    // Fetch a fresh blockhash & sign so we know the signature up front
+    const buildSpan = pingSpan.child('build');
+    let latestBlockhash;
+    let blockhashFetchedAt;
+    try {
+      latestBlockhash = await connection.getLatestBlockhash(commitmentLevel);
+      blockhashFetchedAt = Date.now();
+    } catch (e) {
+      console.log(`${new Date().toISOString()} ERROR: Unable to obtain a new blockhash`);
+      buildSpan.end(e);
+      pingSpan.end(e);
+      if (noteRateLimit('blockhash', e)) await new Promise(r => setTimeout(r, rateLimitedDelay(SLEEP_MS)));
+      continue;
+    }
+    const wallet = WALLETS[walletIndex++ % WALLETS.length];
+    const experimentCell = EXPERIMENT ? EXPERIMENT.next() : {};
+    const feeFromProvider = PRIORITY_FEE_PROVIDER && experimentCell.compute_unit_price === undefined;
+    let priorityFee = feeFromProvider ? globalPriorityFees.fee : experimentCell.compute_unit_price;
+    const transactionType = experimentCell.transaction_type || 'transfer';
+    // A CU limit is only set along with a price. A padded ping needs one for
+    // its memo, and the compute budget instructions count towards its size,
+    // so settle them before sizing the memo.
+    if (experimentCell.compute_unit_limit !== undefined || experimentCell.tx_size) priorityFee ??= 0;
+    const baseComputeUnitLimit = Number(experimentCell.compute_unit_limit ?? COMPUTE_UNIT_LIMIT);
+    // The limit is a fixed width field, so raising it for the memo below
+    // doesn't change the size
+    const memoBytes = experimentCell.tx_size
+      ? paddingBytes(wallet.keypair, priorityFee, baseComputeUnitLimit, experimentCell.tx_size, transactionType)
+      : 0;
+    const computeUnitLimit = baseComputeUnitLimit + memoComputeUnits(memoBytes, transactionType);
+    const tx = buildPingTransaction(wallet.keypair, priorityFee, computeUnitLimit, memoBytes, transactionType);
+    const pingCommitment = experimentCell.commitment_level ?? commitmentLevel;
+    pingSpan.setAttributes({ 'commitment': pingCommitment });
+    tx.recentBlockhash = latestBlockhash.blockhash;
+    tx.lastValidBlockHeight = latestBlockhash.lastValidBlockHeight;
+    tx.sign(wallet.keypair);
+    const txSignature = bs58.encode(tx.signature);
+    buildSpan.end();
+    pingSpan.setAttributes({ 'signature': txSignature });
+
     // Send the TX to the cluster
+    const rawTransaction = tx.serialize();
     const txStart = new Date();
+    pingState.blockhashAgeMs = txStart.getTime() - blockhashFetchedAt;
+    const slotSent = freshSlot(SLOT_MAX_AGE_MS);
+    const slotsToNextLeader = slotsUntilLeaderRotation(slotSent);
+    setLogContext({
+      signature: txSignature,
+      slot_sent: slotSent,
+      priority_fee: priorityFee
+    });
+    let slotLanded;
+    let resender;
+    let sent = false;
+    // ms from txStart until the first sendTransaction response
+    let sendMs;
+    // The provider and region that accepted the TX first
+    let sendProvider;
+    let sendRegion;
+    const targets = experimentCell.send_via
+      ? variantSendConnections(experimentCell.send_via)
+      : pingSendConnections();
+    const commitmentWatcher = MEASURE_ALL_COMMITMENTS
+      ? watchCommitmentLevels(connection, txSignature, txStart.getTime())
+      : undefined;
     try {
-      signature = await web3.sendAndConfirmTransaction(
-        connection,
-        tx,
-        [USER_KEYPAIR],
-        { commitment: commitmentLevel }
+      const sendResults = await sendAndTime(
+        rawTransaction,
+        { preflightCommitment: commitmentLevel },
+        'send',
+        pingSpan,
+        targets
       );
+      sent = true;
+      sendMs = Date.now() - txStart.getTime();
+      transactionsSentTotal.inc();
+      emitPingEvent('sent', txSignature, {
+        slot_sent: slotSent,
+        priority_fee_micro_lamports: priorityFee
+      });
+      ({ provider: sendProvider, region: sendRegion } = sendResults
+        .filter(({ error }) => !error)
+        .sort((a, b) => a.ms - b.ms)[0]);
+      sendResults.forEach(({ label, provider, ms, error }) => {
+        if (error) {
+          console.log(`${new Date().toISOString()} ERROR: Send via ${label} (${provider}) failed after ${ms}ms: ${error.message}`);
+        } else if (VERBOSE_LOG) {
+          console.log(`${new Date().toISOString()} Sent via ${label} (${provider}) in ${ms}ms`);
+        }
+      });
+      resender = startResending(
+        () => sendAndTime(
+          rawTransaction,
+          { skipPreflight: true, maxRetries: 0 },
+          'resend',
+          pingSpan,
+          targets
+        ).then((results) => {
+          emitPingEvent('resent', txSignature);
+          return results;
+        }),
+        latestBlockhash.lastValidBlockHeight,
+        {
+          intervalMs: RESEND_INTERVAL_MS,
+          maxResends: MAX_RESENDS,
+          backoff: RESEND_BACKOFF
+        }
+      );
+      const confirmationSpan = pingSpan.child('confirmation');
+      await confirmPing(
+        connection,
+        txSignature,
+        latestBlockhash,
+        pingCommitment,
+        SIGNATURE_STATUS_POLL_MS,
+        CONFIRMATION_DEADLINES[pingCommitment]
+      ).catch((e) => {
+        confirmationSpan.end(e);
+        throw e;
+      });
+      confirmationSpan.end();
       txSuccess = true;
+      consecutiveTimeouts = 0;
+      emitPingEvent('confirmed', txSignature, {
+        commitment_level: pingCommitment,
+        time_ms: Date.now() - txStart.getTime()
+      });
+      pingsTotal.inc({ outcome: 'confirmed' });
     } catch (e) {
+      errorCategory = classifyPingError(e, sent);
+      pingErrorsTotal.inc({ category: errorCategory });
+      if (!sent) {
+        pingsTotal.inc({ outcome: 'send_error' });
+        (e.sendResults || []).forEach(({ label, provider, ms, error }) => {
+          console.log(`${new Date().toISOString()} ERROR: Send via ${label} (${provider}) failed after ${ms}ms: ${error.message}`);
+        });
+      }
+      if (e.name === 'TransactionFailedError') {
+        emitPingEvent('failed', txSignature, { error: e.message });
+      }
+
       // Log and loop if we get a bad blockhash.
-      if (e.message.includes('new blockhash')) {
-        console.log(`${new Date().toISOString()} ERROR: Unable to obtain a new blockhash`);
-        continue;
-      } else if (e.message.includes('Blockhash not found')) {
+      if (errorCategory === 'blockhash_not_found') {
         console.log(`${new Date().toISOString()} ERROR: Blockhash not found`);
+        pingSpan.end(e);
         continue;
       }
 
-      // If the transaction expired on the chain. Make a log entry and send
-      // to VA. Otherwise log and loop.
-      if (e.name === 'TransactionExpiredBlockheightExceededError') {
-        console.log(`${new Date().toISOString()} ERROR: Blockhash expired/block height exceeded. TX failure sent to VA.`);
+      // If the transaction expired on the chain, or landed but failed and we
+      // report failures, make a log entry and send to VA. Otherwise log and
+      // loop.
+      if (
+        e.name === 'TransactionExpiredBlockheightExceededError' ||
+        e.name === 'TransactionExpiredTimeoutError'
+      ) {
+        // Fall back to a live call if the watcher's value is missing or stale
+        failureCategory = await classifyTimeout(
+          connection,
+          commitmentLevel,
+          tx.lastValidBlockHeight,
+          freshBlockHeight(BLOCK_HEIGHT_MAX_AGE_MS)
+        );
+        failureMessage = e.message;
+        pingsTotal.inc({ outcome: failureCategory });
+        confirmationTimeoutsTotal.inc({ category: failureCategory });
+        trackTimedOutSignature(txSignature, txStart.getTime(), LATE_LANDING_GRACE_MS);
+        emitPingEvent('timed_out', txSignature, { reason: failureCategory });
+        if (++consecutiveTimeouts >= ALERT_TIMEOUT_STREAK) {
+          notify('timeouts', `${consecutiveTimeouts} pings in a row timed out (latest: ${failureCategory})`);
+        }
+        console.log(`${new Date().toISOString()} ERROR: TX timed out (${failureCategory}, lastValidBlockHeight: ${tx.lastValidBlockHeight}). TX failure sent to VA.`);
+      } else if (e.name === 'TransactionFailedError' && REPORT_FAILURES) {
+        failureCategory = 'on_chain_error';
+        failureMessage = e.message;
+        pingsTotal.inc({ outcome: failureCategory });
+        failedTransactionsTotal.inc();
+        console.log(`${new Date().toISOString()} ERROR: ${e.message}. TX failure sent to VA.`);
       } else {
-        console.log(`${new Date().toISOString()} ERROR: ${e.name}`);
+        if (e.name === 'TransactionFailedError') {
+          pingsTotal.inc({ outcome: 'on_chain_error' });
+          failedTransactionsTotal.inc();
+        } else if (sent) {
+          pingsTotal.inc({ outcome: 'error' });
+        }
+        console.log(`${new Date().toISOString()} ERROR: ${e.name} (${errorCategory})`);
         console.log(e.message);
         console.log(e);
         console.log(JSON.stringify(e));
+        pingSpan.end(e);
         continue;
       }
-
-      // Need to submit a fake signature to pass the import filters
-      signature = '9999999999999999999999999999999999999999999999999999999999999999999999999999999999999999';
       txSuccess = false;
-    } 
+    } finally {
+      if (resender) {
+        resender.stop();
+        resendsTotal.inc({}, resender.resends);
+      }
+      // Only successful TXs go on to be watched until finalized
+      if (commitmentWatcher && !txSuccess) commitmentWatcher.stop();
+    }
     const txEnd = new Date();
     const txElapsedMs = txEnd - txStart;
+    pingState.lastPingAt = txEnd.getTime();
+    pingState.lastPingSuccess = txSuccess;
+    if (txSuccess) {
+      slotLanded = await fetchLandedSlot(connection, txSignature)
+        .catch(() => undefined);
+    }
+    // ms from txStart until the landing slot started, by our slot feed. At or
+    // below sendMs if the TX landed in a slot that had already started.
+    const inclusionMs = txSuccess && slotSeenAt(slotLanded) !== undefined
+      ? slotSeenAt(slotLanded) - txStart.getTime()
+      : undefined;
+    const leaderSent = leaderForSlot(slotSent);
+    const leaderLanded = leaderForSlot(slotLanded);
+    recordLeaderResult(
+      leaderSent,
+      txSuccess,
+      slotLanded === undefined || slotSent === undefined ? undefined : slotLanded - slotSent,
+      LEADER_STATS_WINDOW
+    );
+    recordLanding(txSuccess);
+    recordPingSummary(
+      txSuccess,
+      txSuccess ? txElapsedMs : undefined,
+      slotLanded === undefined || slotSent === undefined ? undefined : slotLanded - slotSent
+    );
+    const resends = resender ? resender.resends : 0;
+
+    // Wait for the remaining commitment levels before reporting
+    let commitmentTimes = {};
+    if (commitmentWatcher && txSuccess) {
+      commitmentTimes = await commitmentWatcher.waitForFinalized(
+        SIGNATURE_STATUS_POLL_MS,
+        FINALIZED_TIMEOUT_MS
+      );
+      commitmentWatcher.stop();
+    }
+    const deltas = commitmentDeltas(commitmentTimes);
+
+    let txDetails = {};
+    if (FETCH_TX_DETAILS && txSuccess) {
+      txDetails = await fetchTransactionDetails(RPC_ENDPOINT, txSignature, slotLanded)
+        .catch((e) => {
+          console.log(`${new Date().toISOString()} ERROR: Unable to fetch TX details: ${e.message}`);
+          return {};
+        });
+    }
+    // Landed TXs pay a fee whether or not they succeeded
+    const landed = txSuccess || failureCategory === 'on_chain_error';
+    const feePaid = landed ? txDetails.fee ?? estimatePingFee(priorityFee, computeUnitLimit) : 0;
+    if (landed) {
+      feesPaidLamportsTotal.inc({ source: txDetails.fee === undefined ? 'estimate' : 'meta' }, feePaid);
+    }
+    recordSpend(wallet.label, feePaid);
+    recordFee(feePaid);
 
     // prepare the payload to send to validators.app
-    const payload = JSON.stringify({
+    const pingResult = pingPayload(txSignature, {
       time: txElapsedMs,
-      signature: signature,
-      transaction_type: 'transfer',
+      transaction_type: transactionType,
       success: txSuccess,
-      application: 'web3',
-      commitment_level: commitmentLevel
+      commitment_level: pingCommitment,
+      reason: failureCategory,
+      resends: resends,
+      processed_time: commitmentTimes.processed,
+      confirmed_time: commitmentTimes.confirmed,
+      finalized_time: commitmentTimes.finalized,
+      slot_sent: slotSent,
+      slot_landed: slotLanded,
+      leader_sent: leaderSent,
+      leader_landed: leaderLanded,
+      slots_to_next_leader: slotsToNextLeader,
+      priority_fee_micro_lamports: priorityFee,
+      priority_fee_percentile: feeFromProvider && PRIORITY_FEE_PROVIDER.usesPercentile
+        ? globalPriorityFees.percentile
+        : undefined,
+      priority_fee_strategy: feeFromProvider ? globalPriorityFees.strategy : 'experiment',
+      compute_unit_limit: computeUnitLimit,
+      tx_size: rawTransaction.length,
+      compute_units_consumed: txDetails.computeUnitsConsumed,
+      fee_lamports: txDetails.fee,
+      tx_index: txDetails.txIndex,
+      rpc_rtt_ms: globalRtt.ms,
+      send_ms: sendMs,
+      inclusion_ms: inclusionMs
     });
+    const payload = JSON.stringify(pingResult);
+    // The result plus what only we know about the ping
+    const pingRecord = {
+      ...pingResult,
+      pinger_name: PINGER_NAME,
+      region: PINGER_REGION,
+      sent_at: txStart.toISOString(),
+      tx_signature: txSignature,
+      error: failureMessage,
+      error_category: errorCategory,
+      processed_to_confirmed_ms: deltas.processedToConfirmed,
+      confirmed_to_finalized_ms: deltas.confirmedToFinalized,
+      send_endpoint: {
+        failover: endpointPool.activeLabel(),
+        rotate: targets[0].label
+      }[RPC_SEND_MODE],
+      send_provider: sendProvider,
+      send_region: sendRegion,
+      wallet: wallet.label,
+      experiment: EXPERIMENT ? experimentLabel(experimentCell) : undefined,
+      variant: experimentCell.variant
+    };
+    logEvent('ping', { ...pingResult, time_ms: pingResult.time });
 
     if (VERBOSE_LOG) {
       console.log(`${new Date().toISOString()} ${payload}`);
+      if (commitmentWatcher) {
+        console.log(`${new Date().toISOString()} processed => confirmed: ${deltas.processedToConfirmed}ms, confirmed => finalized: ${deltas.confirmedToFinalized}ms`);
+      }
+      console.log(`${new Date().toISOString()} Sent in slot ${slotSent} (leader: ${leaderSent}, ${slotsToNextLeader} slots to next leader), landed in slot ${slotLanded} (leader: ${leaderLanded})`);
+      if (txDetails.computeUnitsConsumed !== undefined) {
+        const limit = priorityFee === undefined ? 'default limit' : `limit ${computeUnitLimit}`;
+        console.log(`${new Date().toISOString()} Compute units: ${txDetails.computeUnitsConsumed} consumed (${limit}), fee paid: ${txDetails.fee} lamports`);
+      }
+      if (RPC_SEND_MODE === 'failover') {
+        console.log(`${new Date().toISOString()} Active send endpoint: ${endpointPool.activeLabel()}`);
+      }
+      console.log(`${new Date().toISOString()} Block height: ${globalBlockHeight.blockHeight} (lag: ${blockHeightLagMs()}ms)`);
     }
 
-    // Send the ping data to validators.app
-    restClient.open(
-      'POST',
-      'https://www.validators.app/api/v1/ping-thing/mainnet'
-    );
-    restClient.setRequestHeader('Content-Type', 'application/json');
-    restClient.setRequestHeader('Token', VA_API_KEY);
-    restClient.send(payload);
+    reportPing(reporters, { result: pingResult, record: pingRecord, span: pingSpan });
+    recordRecentPing(pingRecord);
+    emitPingEvent('result', txSignature, { ...pingRecord, time_ms: pingRecord.time });
+    pingSpan.setAttributes({ 'success': txSuccess, 'reason': failureCategory });
+    pingSpan.end();
+    setLogContext({});
 
     // Reset the try counter and sleep
     tryCount = 0;
     await new Promise(r => setTimeout(r, SLEEP_MS));
   } catch (e) {
     console.log('\n', e, '\n');
-    if (++tryCount === maxTries) throw e;
+    pingSpan.end(e);
+    if (++tryCount === maxTries) {
+      await reportCrash(e);
+      throw e;
+    }
   }
 }
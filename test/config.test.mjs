@@ -0,0 +1,87 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+import { parseTomlSections, parseConfigFile } from '../utils/config.mjs';
+
+function tomlFile(text) {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'ping-thing-test-'));
+  const file = path.join(dir, 'config.toml');
+  fs.writeFileSync(file, text);
+  return file;
+}
+
+test('parses values by type', () => {
+  const sections = parseTomlSections(tomlFile([
+    'name = "pinger"',
+    "literal = 'a\\b'",
+    'sleep_ms = 5_000',
+    'ratio = 0.5',
+    'verbose = true',
+    'empty = []'
+  ].join('\n')));
+  assert.deepEqual(sections[''], {
+    name: 'pinger',
+    literal: 'a\\b',
+    sleep_ms: 5000,
+    ratio: 0.5,
+    verbose: true,
+    empty: []
+  });
+});
+
+test('strips comments but not a # inside quotes', () => {
+  const sections = parseTomlSections(tomlFile([
+    '# a comment',
+    'url = "http://host/#anchor" # trailing',
+    "tag = 'a#b'",
+    'count = 3 # three'
+  ].join('\n')));
+  assert.deepEqual(sections[''], { url: 'http://host/#anchor', tag: 'a#b', count: 3 });
+});
+
+test('splits arrays on commas outside quotes', () => {
+  const sections = parseTomlSections(tomlFile([
+    'list = ["a,b", \'c\', 3, "say \\"hi, there\\""]'
+  ].join('\n')));
+  assert.deepEqual(sections[''].list, ['a,b', 'c', 3, 'say "hi, there"']);
+});
+
+test('parses arrays spanning lines', () => {
+  const sections = parseTomlSections(tomlFile([
+    'endpoints = [',
+    '  "http://a", # first',
+    '  "http://b",',
+    ']',
+    'after = 1'
+  ].join('\n')));
+  assert.deepEqual(sections[''], { endpoints: ['http://a', 'http://b'], after: 1 });
+});
+
+test('groups keys by section', () => {
+  const file = tomlFile([
+    'sleep_ms = 1000',
+    '[influxdb]',
+    'write_url = "http://influx"',
+    '[variant.cheap-fee]',
+    'compute_unit_price = 0'
+  ].join('\n'));
+  assert.deepEqual(parseTomlSections(file), {
+    '': { sleep_ms: 1000 },
+    influxdb: { write_url: 'http://influx' },
+    'variant.cheap-fee': { compute_unit_price: 0 }
+  });
+  assert.deepEqual(parseConfigFile(file), {
+    SLEEP_MS: 1000,
+    INFLUXDB_WRITE_URL: 'http://influx',
+    VARIANT_CHEAP_FEE_COMPUTE_UNIT_PRICE: 0
+  });
+});
+
+test('reports the line of a malformed entry', () => {
+  const file = tomlFile('sleep_ms = 1000\nnot a setting\n');
+  assert.throws(() => parseTomlSections(file), { message: `${file}:2: expected key = value` });
+  const value = tomlFile('sleep_ms = soon\n');
+  assert.throws(() => parseTomlSections(value), { message: `${value}:1: can't parse value soon` });
+});
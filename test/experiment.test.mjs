@@ -0,0 +1,102 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+import {
+  createExperiment,
+  createVariantExperiment,
+  experimentFromEnv,
+  experimentLabel
+} from '../utils/experiment.mjs';
+
+const take = (experiment, n) => Array.from({ length: n }, () => experiment.next());
+
+test('visits every cell of the grid in turn', () => {
+  const experiment = createExperiment({ compute_unit_limit: [300, 1000], compute_unit_price: [0, 10] });
+  assert.equal(experiment.size, 4);
+  assert.deepEqual(take(experiment, 5), [
+    { compute_unit_limit: 300, compute_unit_price: 0 },
+    { compute_unit_limit: 300, compute_unit_price: 10 },
+    { compute_unit_limit: 1000, compute_unit_price: 0 },
+    { compute_unit_limit: 1000, compute_unit_price: 10 },
+    { compute_unit_limit: 300, compute_unit_price: 0 }
+  ]);
+});
+
+test('labels cells without the variant name', () => {
+  assert.equal(
+    experimentLabel({ variant: 'cheap', compute_unit_limit: 300, compute_unit_price: 0 }),
+    'compute_unit_limit=300,compute_unit_price=0'
+  );
+});
+
+test('interleaves variants by weight', () => {
+  const experiment = createVariantExperiment([
+    { variant: 'a', weight: 1 },
+    { variant: 'b', weight: 3 },
+    { variant: 'c', weight: 2 }
+  ], 'interleave');
+  assert.equal(experiment.size, 3);
+  assert.deepEqual(take(experiment, 7).map((cell) => cell.variant), ['a', 'b', 'c', 'b', 'c', 'b', 'a']);
+});
+
+test('picks random variants in proportion to weight', (t) => {
+  const experiment = createVariantExperiment([
+    { variant: 'a', weight: 1 },
+    { variant: 'b', weight: 3 }
+  ], 'random');
+  const picks = [0, 0.24, 0.25, 0.99];
+  t.mock.method(Math, 'random', () => picks.shift());
+  assert.deepEqual(take(experiment, 4).map((cell) => cell.variant), ['a', 'a', 'b', 'b']);
+});
+
+test('builds a grid from the EXPERIMENT_* lists', () => {
+  const experiment = experimentFromEnv({
+    EXPERIMENT_CU_PRICES: '0, 1000',
+    EXPERIMENT_COMMITMENTS: 'confirmed,finalized'
+  });
+  assert.deepEqual(experiment.cells, [
+    { compute_unit_price: 0, commitment_level: 'confirmed' },
+    { compute_unit_price: 0, commitment_level: 'finalized' },
+    { compute_unit_price: 1000, commitment_level: 'confirmed' },
+    { compute_unit_price: 1000, commitment_level: 'finalized' }
+  ]);
+  assert.equal(experimentFromEnv({}), undefined);
+});
+
+test('rejects values out of range', () => {
+  assert.throws(() => experimentFromEnv({ EXPERIMENT_CU_LIMITS: '300,0' }), {
+    message: 'EXPERIMENT_CU_LIMITS values must be a whole number from 1 to 1400000'
+  });
+  assert.throws(() => experimentFromEnv({ EXPERIMENT_TX_SIZES: '2000' }), /EXPERIMENT_TX_SIZES values/);
+  assert.throws(() => experimentFromEnv({ EXPERIMENT_COMMITMENTS: 'landed' }), {
+    message: 'EXPERIMENT_COMMITMENTS values must be one of processed, confirmed, finalized'
+  });
+});
+
+test('loads weighted variants from a file', () => {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'ping-thing-test-'));
+  const file = path.join(dir, 'experiment.toml');
+  fs.writeFileSync(file, [
+    '[variant.cheap]',
+    'compute_unit_price = 0',
+    '[variant.priority]',
+    'compute_unit_price = 10000',
+    'weight = 2'
+  ].join('\n'));
+  const experiment = experimentFromEnv({ EXPERIMENT_FILE: file });
+  assert.deepEqual(take(experiment, 3), [
+    { variant: 'cheap', compute_unit_price: 0 },
+    { variant: 'priority', compute_unit_price: 10000 },
+    { variant: 'priority', compute_unit_price: 10000 }
+  ]);
+
+  fs.writeFileSync(file, '[variant.bad]\ncolour = "red"\n');
+  assert.throws(() => experimentFromEnv({ EXPERIMENT_FILE: file }), {
+    message: `${file}: unknown parameter colour in variant.bad`
+  });
+  assert.throws(() => experimentFromEnv({ EXPERIMENT_FILE: file, EXPERIMENT_CU_PRICES: '0' }), {
+    message: 'Set EXPERIMENT_FILE or the EXPERIMENT_* lists, not both'
+  });
+});
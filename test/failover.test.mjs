@@ -0,0 +1,97 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { createEndpointPool } from '../utils/failover.mjs';
+
+// Send connections whose sends fail with the next error in `errors`, if any
+function endpoint(label, errors = []) {
+  const sent = [];
+  return {
+    label,
+    provider: `${label}-provider`,
+    region: 'fra',
+    sent,
+    connection: {
+      async sendRawTransaction(rawTransaction) {
+        sent.push(rawTransaction);
+        const error = errors.shift();
+        if (error) throw new Error(error);
+      }
+    }
+  };
+}
+
+test('sends through the first endpoint while it works', async () => {
+  const [a, b] = [endpoint('a'), endpoint('b')];
+  const pool = createEndpointPool([a, b], { failureThreshold: 2, resetMs: 60000 });
+  const results = await pool.send('tx1', {});
+  await pool.send('tx2', {});
+  assert.equal(results.length, 1);
+  assert.equal(results[0].label, 'a');
+  assert.equal(results[0].error, undefined);
+  assert.deepEqual(a.sent, ['tx1', 'tx2']);
+  assert.deepEqual(b.sent, []);
+});
+
+test('opens the circuit after failureThreshold failures in a row', async () => {
+  const [a, b] = [endpoint('a', ['socket hang up', 'socket hang up']), endpoint('b')];
+  const pool = createEndpointPool([a, b], { failureThreshold: 2, resetMs: 60000 });
+  await assert.rejects(pool.send('tx1', {}), (e) => e.sendResults[0].label === 'a');
+  assert.equal(pool.activeLabel(), 'a');
+  await assert.rejects(pool.send('tx2', {}));
+  await pool.send('tx3', {});
+  assert.equal(pool.activeLabel(), 'b');
+  assert.equal(pool.activeProvider(), 'b-provider');
+  assert.deepEqual(b.sent, ['tx3']);
+});
+
+test('a success resets the failure count', async () => {
+  const [a, b] = [endpoint('a', ['socket hang up', undefined, 'socket hang up']), endpoint('b')];
+  const pool = createEndpointPool([a, b], { failureThreshold: 2, resetMs: 60000 });
+  await assert.rejects(pool.send('tx1', {}));
+  await pool.send('tx2', {});
+  await assert.rejects(pool.send('tx3', {}));
+  await pool.send('tx4', {});
+  assert.deepEqual(a.sent, ['tx1', 'tx2', 'tx3', 'tx4']);
+});
+
+test('opens the circuit at once on a 429 but not on other numbers', async () => {
+  const [a, b] = [endpoint('a', ['Server responded with 429 Too Many Requests']), endpoint('b')];
+  const pool = createEndpointPool([a, b], { failureThreshold: 5, resetMs: 60000 });
+  await assert.rejects(pool.send('tx1', {}));
+  await pool.send('tx2', {});
+  assert.deepEqual(b.sent, ['tx2']);
+
+  const [c, d] = [endpoint('c', ['blockhash 14290 not found']), endpoint('d')];
+  const other = createEndpointPool([c, d], { failureThreshold: 5, resetMs: 60000 });
+  await assert.rejects(other.send('tx1', {}));
+  await other.send('tx2', {});
+  assert.deepEqual(c.sent, ['tx1', 'tx2']);
+});
+
+test('preflight failures do not count against the endpoint', async () => {
+  const [a, b] = [endpoint('a', ['Transaction simulation failed: blockhash not found']), endpoint('b')];
+  const pool = createEndpointPool([a, b], { failureThreshold: 1, resetMs: 60000 });
+  await assert.rejects(pool.send('tx1', {}));
+  await pool.send('tx2', {});
+  assert.deepEqual(a.sent, ['tx1', 'tx2']);
+});
+
+test('closes the circuit again after resetMs', async () => {
+  const [a, b] = [endpoint('a', ['socket hang up']), endpoint('b')];
+  const pool = createEndpointPool([a, b], { failureThreshold: 1, resetMs: 20 });
+  await assert.rejects(pool.send('tx1', {}));
+  await pool.send('tx2', {});
+  await new Promise((resolve) => setTimeout(resolve, 30));
+  await pool.send('tx3', {});
+  assert.deepEqual(a.sent, ['tx1', 'tx3']);
+  assert.deepEqual(b.sent, ['tx2']);
+});
+
+test('uses the endpoint that reopens first when every circuit is open', async () => {
+  const [a, b] = [endpoint('a', ['socket hang up']), endpoint('b', ['socket hang up'])];
+  const pool = createEndpointPool([a, b], { failureThreshold: 1, resetMs: 60000 });
+  await assert.rejects(pool.send('tx1', {}));
+  await assert.rejects(pool.send('tx2', {}));
+  await pool.send('tx3', {});
+  assert.deepEqual(a.sent, ['tx1', 'tx3']);
+});
@@ -0,0 +1,24 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { pingResultLine } from '../utils/influx.mjs';
+
+test('writes tags and typed fields', () => {
+  const line = pingResultLine(
+    { time: 850, success: true, slot_sent: 100, slot_landed: 102, priority_fee_micro_lamports: 1.5, signature: 'abc' },
+    { pingerName: 'pinger', region: 'fra', timeMs: 1700000000000 }
+  );
+  assert.equal(line, 'ping_thing,pinger=pinger,region=fra time_ms=850i,success=true,slot_latency=2i,fee=1.5,signature="abc" 1700000000000');
+});
+
+test('escapes commas, equals signs, spaces and backslashes in tags', () => {
+  const line = pingResultLine(
+    { time: 1, reason: 'a=b, c\\d' },
+    { pingerName: 'my pinger', region: '', timeMs: 1 }
+  );
+  assert.equal(line, 'ping_thing,pinger=my\\ pinger,reason=a\\=b\\,\\ c\\\\d time_ms=1i 1');
+});
+
+test('escapes quotes and backslashes in string fields', () => {
+  const line = pingResultLine({ signature: 'say "hi" \\o/' }, { pingerName: 'p', timeMs: 1 });
+  assert.equal(line, 'ping_thing,pinger=p signature="say \\"hi\\" \\\\o/" 1');
+});
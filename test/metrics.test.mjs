@@ -0,0 +1,56 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { counter, gauge, histogram, renderMetrics, setDefaultLabels } from '../utils/metrics.mjs';
+
+setDefaultLabels({ pinger_name: 'test' });
+
+const pings = counter('test_pings_total', 'Pings sent');
+const balance = gauge('test_balance', 'Wallet balance');
+let leaders = [];
+gauge('test_leader_pings', 'Pings per leader', () => leaders);
+const latency = histogram('test_latency_ms', 'Ping latency', [100, 1000]);
+
+const series = (name) => renderMetrics().split('\n').filter((l) => l.startsWith(name));
+
+test('renders help and type for every metric', () => {
+  const text = renderMetrics();
+  assert.ok(text.includes('# HELP test_pings_total Pings sent\n# TYPE test_pings_total counter\n'));
+  assert.ok(text.includes('# TYPE test_latency_ms histogram\n'));
+  assert.ok(text.endsWith('\n'));
+});
+
+test('sums counters per label set', () => {
+  pings.inc({ outcome: 'landed' });
+  pings.inc({ outcome: 'landed' }, 2);
+  pings.inc({ outcome: 'dropped' });
+  assert.deepEqual(series('test_pings_total{'), [
+    'test_pings_total{pinger_name="test",outcome="landed"} 3',
+    'test_pings_total{pinger_name="test",outcome="dropped"} 1'
+  ]);
+});
+
+test('escapes label values', () => {
+  balance.set({ wallet: 'a"b\\c\nd' }, 5);
+  assert.deepEqual(series('test_balance{'), ['test_balance{pinger_name="test",wallet="a\\"b\\\\c\\nd"} 5']);
+});
+
+test('collects gauges at scrape time and skips missing values', () => {
+  leaders = [{ labels: { leader: 'x' }, value: 2 }, { labels: { leader: 'y' }, value: undefined }];
+  assert.deepEqual(series('test_leader_pings{'), ['test_leader_pings{pinger_name="test",leader="x"} 2']);
+  leaders = [];
+  assert.deepEqual(series('test_leader_pings{'), []);
+});
+
+test('renders cumulative histogram buckets', () => {
+  latency.observe({ commitment: 'confirmed' }, 50);
+  latency.observe({ commitment: 'confirmed' }, 500);
+  latency.observe({ commitment: 'confirmed' }, 5000);
+  latency.observe({ commitment: 'confirmed' }, undefined);
+  assert.deepEqual(series('test_latency_ms'), [
+    'test_latency_ms_bucket{pinger_name="test",commitment="confirmed",le="100"} 1',
+    'test_latency_ms_bucket{pinger_name="test",commitment="confirmed",le="1000"} 2',
+    'test_latency_ms_bucket{pinger_name="test",commitment="confirmed",le="+Inf"} 3',
+    'test_latency_ms_sum{pinger_name="test",commitment="confirmed"} 5550',
+    'test_latency_ms_count{pinger_name="test",commitment="confirmed"} 3'
+  ]);
+});
@@ -0,0 +1,33 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { percentileOf } from '../utils/priorityfees.mjs';
+import { commitmentDeltas } from '../utils/commitments.mjs';
+
+test('percentileOf uses the nearest rank', () => {
+  const fees = [50, 10, 40, 20, 30];
+  assert.equal(percentileOf(fees, 0), 10);
+  assert.equal(percentileOf(fees, 20), 10);
+  assert.equal(percentileOf(fees, 21), 20);
+  assert.equal(percentileOf(fees, 50), 30);
+  assert.equal(percentileOf(fees, 100), 50);
+  assert.deepEqual(fees, [50, 10, 40, 20, 30]);
+});
+
+test('percentileOf of nothing is undefined', () => {
+  assert.equal(percentileOf([], 50), undefined);
+});
+
+test('commitmentDeltas between the levels that were reached', () => {
+  assert.deepEqual(commitmentDeltas({ processed: 400, confirmed: 1200, finalized: 13000 }), {
+    processedToConfirmed: 800,
+    confirmedToFinalized: 11800
+  });
+  assert.deepEqual(commitmentDeltas({ processed: 400, confirmed: 1200 }), {
+    processedToConfirmed: 800,
+    confirmedToFinalized: undefined
+  });
+  assert.deepEqual(commitmentDeltas({}), {
+    processedToConfirmed: undefined,
+    confirmedToFinalized: undefined
+  });
+});
@@ -0,0 +1,34 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import crypto from 'crypto';
+import { encryptSecretKey, decryptSecretKey } from '../utils/wallet.mjs';
+
+const secretKey = crypto.randomBytes(64);
+const file = encryptSecretKey(secretKey, 'correct horse');
+
+test('decrypts what it encrypted', () => {
+  assert.equal(file.kdf, 'scrypt');
+  assert.equal(file.cipher, 'aes-256-gcm');
+  assert.ok(!Buffer.from(file.ciphertext, 'base64').equals(secretKey));
+  assert.deepEqual(decryptSecretKey(file, 'correct horse'), secretKey);
+});
+
+test('rejects the wrong passphrase', () => {
+  assert.throws(() => decryptSecretKey(file, 'battery staple'), {
+    message: 'wrong passphrase for the encrypted keypair'
+  });
+});
+
+test('rejects a tampered ciphertext', () => {
+  const ciphertext = Buffer.from(file.ciphertext, 'base64');
+  ciphertext[0] ^= 1;
+  assert.throws(() => decryptSecretKey({ ...file, ciphertext: ciphertext.toString('base64') }, 'correct horse'), {
+    message: 'wrong passphrase for the encrypted keypair'
+  });
+});
+
+test('rejects other encryption schemes', () => {
+  assert.throws(() => decryptSecretKey({ ...file, kdf: 'pbkdf2' }, 'correct horse'), {
+    message: 'unsupported keypair encryption pbkdf2/aes-256-gcm'
+  });
+});
@@ -0,0 +1,24 @@
+// Top up wallets on devnet and testnet with airdrops, so long running
+// pingers there don't need manual refills. Faucets are rate limited and
+// flaky, so each top up retries with backoff.
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+
+const AIRDROP_ATTEMPTS = 5;
+
+// Request `lamports` for `wallet` ({ label, keypair }) and wait for it to
+// confirm. Resolves to whether the airdrop landed.
+export async function airdrop(connection, wallet, lamports) {
+  for (let attempt = 1; attempt <= AIRDROP_ATTEMPTS; attempt++) {
+    try {
+      const signature = await connection.requestAirdrop(wallet.keypair.publicKey, lamports);
+      const result = await connection.confirmTransaction(signature, 'confirmed');
+      if (result.value.err) throw new Error(JSON.stringify(result.value.err));
+      console.log(`${timestamp()} Airdropped ${lamports} lamports to wallet ${wallet.label} (${signature})`);
+      return true;
+    } catch (e) {
+      console.log(`${timestamp()} ERROR: Airdrop to wallet ${wallet.label} failed (attempt ${attempt} of ${AIRDROP_ATTEMPTS}): ${e.message}`);
+      if (attempt < AIRDROP_ATTEMPTS) await sleep(backoffMs(attempt, 2000));
+    }
+  }
+  return false;
+}
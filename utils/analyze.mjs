@@ -0,0 +1,109 @@
+// analyze: summarize the ping history in PING_SQLITE_DB (or PING_LOG_FILE)
+// as tables of landing rate and latency by fee bucket, by hour and by
+// experiment, as aligned text or CSV.
+import { loadPingRecords } from './pingstore.mjs';
+import { percentileOf } from './priorityfees.mjs';
+import { feeBucket, feeBucketLabel } from './feetarget.mjs';
+
+const TABLES = ['fees', 'hours', 'experiments'];
+const FORMATS = ['text', 'csv'];
+
+// Pings as { sent_at, success, time, fee, slots, txIndex, experiment }.
+// slots is from send to landing. txIndex, the position in the landing block,
+// needs FETCH_TX_DETAILS.
+async function loadPings(env, sinceMs) {
+  const records = await loadPingRecords(env, sinceMs);
+  const known = (value) => value !== null && value !== undefined;
+  return records.map((record) => ({
+    sent_at: record.sent_at,
+    success: record.success,
+    time: record.time,
+    fee: record.priority_fee_micro_lamports || 0,
+    slots: known(record.slot_landed) && known(record.slot_sent)
+      ? record.slot_landed - record.slot_sent
+      : undefined,
+    txIndex: known(record.tx_index) ? record.tx_index : undefined,
+    experiment: record.variant || record.experiment || undefined
+  }));
+}
+
+// One row of stats for a group of pings
+function summarize(pings) {
+  const landed = pings.filter(({ success }) => success);
+  const times = landed.map(({ time }) => time);
+  const slots = landed.map(({ slots }) => slots).filter((s) => s !== undefined);
+  const txIndices = landed.map(({ txIndex }) => txIndex).filter((i) => i !== undefined);
+  return {
+    pings: pings.length,
+    landing_rate: (landed.length / pings.length).toFixed(3),
+    p50_ms: percentileOf(times, 50),
+    p90_ms: percentileOf(times, 90),
+    p99_ms: percentileOf(times, 99),
+    median_slots: percentileOf(slots, 50),
+    median_tx_index: percentileOf(txIndices, 50),
+    median_fee: percentileOf(pings.map(({ fee }) => fee), 50)
+  };
+}
+
+// { key: [pings] } sorted by key
+function groupBy(pings, keyOf) {
+  const groups = new Map();
+  pings.forEach((ping) => {
+    const key = keyOf(ping);
+    if (key === undefined) return;
+    groups.set(key, [...(groups.get(key) || []), ping]);
+  });
+  return [...groups.entries()].sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0));
+}
+
+// Each table is a list of row objects
+function buildTables(pings) {
+  return {
+    // The fee-vs-latency curve and landing rate per fee bucket
+    fees: groupBy(pings, ({ fee }) => feeBucket(fee))
+      .map(([bucket, group]) => ({ fee_micro_lamports: feeBucketLabel(bucket), ...summarize(group) })),
+    hours: groupBy(pings, ({ sent_at: sentAt }) => sentAt.slice(0, 13) + ':00Z')
+      .map(([hour, group]) => ({ hour, ...summarize(group) })),
+    experiments: groupBy(pings, ({ experiment }) => experiment)
+      .map(([experiment, group]) => ({ experiment, ...summarize(group) }))
+  };
+}
+
+const cell = (value) => (value === undefined || value === null ? '' : String(value));
+
+function renderText(rows) {
+  if (!rows.length) return '(no pings)';
+  const columns = Object.keys(rows[0]);
+  const widths = columns.map((c) => Math.max(c.length, ...rows.map((row) => cell(row[c]).length)));
+  const line = (values) => values.map((v, i) => v.padEnd(widths[i])).join('  ').trimEnd();
+  return [line(columns), ...rows.map((row) => line(columns.map((c) => cell(row[c]))))].join('\n');
+}
+
+function renderCsv(rows) {
+  if (!rows.length) return '';
+  const columns = Object.keys(rows[0]);
+  const csvCell = (value) => /[",\n]/.test(value) ? `"${value.replace(/"/g, '""')}"` : value;
+  return [columns.join(','), ...rows.map((row) => columns.map((c) => csvCell(cell(row[c]))).join(','))]
+    .join('\n');
+}
+
+// Resolves to the exit code. ANALYZE_TABLE picks one table, which CSV
+// output needs (default fees). ANALYZE_WINDOW_HOURS limits the history.
+export async function analyzeCommand(env) {
+  const format = env.ANALYZE_FORMAT || 'text';
+  if (!FORMATS.includes(format)) throw new Error(`ANALYZE_FORMAT must be one of ${FORMATS.join(', ')}`);
+  const table = env.ANALYZE_TABLE || (format === 'csv' ? 'fees' : undefined);
+  if (table && !TABLES.includes(table)) throw new Error(`ANALYZE_TABLE must be one of ${TABLES.join(', ')}`);
+  const windowHours = Number(env.ANALYZE_WINDOW_HOURS || 0);
+
+  const pings = await loadPings(env, windowHours ? Date.now() - windowHours * 3600000 : 0);
+  const tables = buildTables(pings);
+  if (format === 'csv') {
+    console.log(renderCsv(tables[table]));
+    return 0;
+  }
+  (table ? [table] : TABLES).forEach((name, i) => {
+    console.log(`${i ? '\n' : ''}== ${name} (${pings.length} pings)\n${renderText(tables[name])}`);
+  });
+  return 0;
+}
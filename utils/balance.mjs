@@ -0,0 +1,52 @@
+// Poll the wallet balances so a drained wallet shows up in logs and metrics
+// instead of as a stream of failed sends.
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+import { rateLimitedDelay, noteRateLimit } from './ratelimit.mjs';
+import { notify } from './notifier.mjs';
+import { airdrop } from './airdrop.mjs';
+
+// Repeat the low balance warning at most this often
+const LOW_BALANCE_WARNING_INTERVAL_MS = 3600000;
+
+// Shared state. wallets holds the last balance seen by wallet label.
+export const globalBalance = {
+  wallets: {},
+  updatedAt: undefined
+};
+
+// wallets is [{ label, keypair }], see loadWallets. With topUp
+// ({ belowLamports, lamports }) wallets below belowLamports get an airdrop.
+export async function watchBalance(connection, wallets, intervalMs, lowBalanceLamports, topUp) {
+  let failures = 0;
+  const lastWarningAt = {};
+  while (true) {
+    try {
+      for (const wallet of wallets) {
+        const { label, keypair } = wallet;
+        let lamports = await connection.getBalance(keypair.publicKey);
+        if (topUp && lamports < topUp.belowLamports && await airdrop(connection, wallet, topUp.lamports)) {
+          lamports = await connection.getBalance(keypair.publicKey);
+        }
+        globalBalance.wallets[label] = lamports;
+
+        const address = keypair.publicKey.toBase58();
+        if (lamports < lowBalanceLamports) {
+          if (Date.now() - (lastWarningAt[label] || 0) > LOW_BALANCE_WARNING_INTERVAL_MS) {
+            console.log(`${timestamp()} WARNING: Wallet ${address} balance is low: ${lamports} lamports`);
+            notify(`low_balance_${label}`, `Wallet ${address} balance is low: ${lamports} lamports`);
+            lastWarningAt[label] = Date.now();
+          }
+        } else {
+          lastWarningAt[label] = 0;
+        }
+      }
+      globalBalance.updatedAt = Date.now();
+      failures = 0;
+    } catch (e) {
+      failures++;
+      noteRateLimit('balance', e);
+      console.log(`${timestamp()} ERROR: Unable to fetch wallet balance (${failures} in a row): ${e.message}`);
+    }
+    await sleep(rateLimitedDelay(failures ? backoffMs(failures, 1000) : intervalMs));
+  }
+}
@@ -0,0 +1,41 @@
+// Keep track of the cluster's block height in the background so the ping loop
+// can check blockhash expiry (lastValidBlockHeight) without an extra RPC call.
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+import { rateLimitedDelay, noteRateLimit } from './ratelimit.mjs';
+
+// Shared state. updatedAt is a millisecond timestamp of the last good poll.
+export const globalBlockHeight = {
+  blockHeight: undefined,
+  updatedAt: undefined
+};
+
+// Poll getBlockHeight forever. Errors are logged and the loop backs off
+// before retrying so a flaky RPC doesn't stop the watcher.
+export async function watchBlockHeight(connection, commitment, intervalMs) {
+  let failures = 0;
+  while (true) {
+    try {
+      globalBlockHeight.blockHeight = await connection.getBlockHeight(commitment);
+      globalBlockHeight.updatedAt = Date.now();
+      failures = 0;
+    } catch (e) {
+      failures++;
+      noteRateLimit('block_height', e);
+      console.log(`${timestamp()} ERROR: Unable to fetch block height (${failures} in a row): ${e.message}`);
+    }
+    await sleep(rateLimitedDelay(failures ? backoffMs(failures, intervalMs) : intervalMs));
+  }
+}
+
+// How many milliseconds old the cached block height is.
+export function blockHeightLagMs() {
+  if (globalBlockHeight.updatedAt === undefined) return undefined;
+  return Date.now() - globalBlockHeight.updatedAt;
+}
+
+// The cached block height if it was updated within maxAgeMs, otherwise
+// undefined.
+export function freshBlockHeight(maxAgeMs) {
+  const lagMs = blockHeightLagMs();
+  return lagMs !== undefined && lagMs <= maxAgeMs ? globalBlockHeight.blockHeight : undefined;
+}
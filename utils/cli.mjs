@@ -0,0 +1,167 @@
+// Command line interface:
+//
+//   node ping-thing-client.mjs [command] [--config file] [--setting value ...]
+//
+// Every setting can be given as a flag named after its env var in lower
+// kebab case, e.g. --sleep-ms 5000 or --verbose-log. Flags beat env vars,
+// which beat the config file.
+import { parseArgs } from 'util';
+import web3 from '@solana/web3.js';
+import { SETTINGS, isSecretSetting, redactSetting } from './config.mjs';
+import { loadWallets, encryptKeypairCommand } from './wallet.mjs';
+import { dryRun } from './dryrun.mjs';
+import { analyzeCommand } from './analyze.mjs';
+import { rpcConnection } from './rpcheaders.mjs';
+import { exportResearchCommand } from './research.mjs';
+
+const flagName = (setting) => setting.toLowerCase().replace(/_/g, '-');
+
+const USAGE = `Usage: node ping-thing-client.mjs [command] [options]
+
+Commands:
+  run              Ping continuously (default)
+  validate-config  Check the settings and exit
+  export-config    Print the effective settings as TOML, with secrets left
+                   out unless --include-secrets is given
+  balance          Print the wallet addresses and balances
+  encrypt-keypair  Print the wallet encrypted with a passphrase, for use as
+                   WALLET_KEYPAIR_PATH
+  dry-run          Check the keypair, RPC endpoints, balance and VA and sign
+                   (but don't send) a ping
+  analyze          Summarize the pings in PING_SQLITE_DB or PING_LOG_FILE by
+                   fee bucket, hour and experiment (--analyze-format csv for
+                   CSV, --analyze-table to pick one table)
+  export-research  Write the stored pings as versioned JSONL for priority fee
+                   research, see research-schema-v1.json
+
+Options:
+  --config <file>  TOML config file (or CONFIG_FILE)
+  --include-secrets
+                   Let export-config print keys, tokens and credentials
+  --help           Show this help
+  --<setting>      Any setting from .env.sample in lower kebab case, e.g.
+                   --rpc-endpoint https://... --sleep-ms 5000 --verbose-log`;
+
+// command => { run(cli), checkRequired }. run resolves to the exit code and
+// gets the parsed command line, see parseCli.
+// checkRequired says whether the required settings must be present.
+const COMMANDS = {
+  'run': { checkRequired: true },
+  'validate-config': {
+    checkRequired: true,
+    run: async () => {
+      console.log('Configuration OK');
+      return 0;
+    }
+  },
+  'export-config': {
+    checkRequired: false,
+    run: async (cli) => {
+      console.log(exportConfig(cli.includeSecrets));
+      return 0;
+    }
+  },
+  'dry-run': {
+    checkRequired: true,
+    run: () => dryRun(process.env)
+  },
+  'analyze': {
+    checkRequired: false,
+    run: () => analyzeCommand(process.env)
+  },
+  'export-research': {
+    checkRequired: false,
+    run: () => exportResearchCommand(process.env)
+  },
+  'encrypt-keypair': {
+    checkRequired: false,
+    run: () => encryptKeypairCommand(process.env)
+  },
+  'balance': {
+    checkRequired: true,
+    run: async () => {
+      const connection = rpcConnection(process.env.RPC_ENDPOINT);
+      for (const { label, keypair } of await loadWallets(process.env)) {
+        const address = keypair.publicKey.toBase58();
+        const lamports = await connection.getBalance(keypair.publicKey);
+        const name = label === address ? address : `${label} ${address}`;
+        console.log(`${name} ${lamports / web3.LAMPORTS_PER_SOL} SOL (${lamports} lamports)`);
+      }
+      return 0;
+    }
+  }
+};
+
+// Parse argv, copying setting flags into process.env. Returns
+// { command, config, checkRequired, includeSecrets } or exits on --help and bad arguments.
+export function parseCli(argv) {
+  const options = {
+    'config': { type: 'string' },
+    'include-secrets': { type: 'boolean' },
+    'help': { type: 'boolean' }
+  };
+  Object.entries(SETTINGS).forEach(([setting, type]) => {
+    options[flagName(setting)] = { type: type === 'boolean' ? 'boolean' : 'string' };
+  });
+
+  let parsed;
+  try {
+    parsed = parseArgs({ args: argv, options, allowPositionals: true });
+  } catch (e) {
+    console.log(`${e.message}\n\n${USAGE}`);
+    process.exit(2);
+  }
+  const command = parsed.positionals[0] || 'run';
+  if (parsed.values.help) {
+    console.log(USAGE);
+    process.exit(0);
+  }
+  if (!COMMANDS[command] || parsed.positionals.length > 1) {
+    console.log(`Unknown command ${parsed.positionals.join(' ')}\n\n${USAGE}`);
+    process.exit(2);
+  }
+
+  Object.keys(SETTINGS).forEach((setting) => {
+    const value = parsed.values[flagName(setting)];
+    if (value !== undefined) process.env[setting] = String(value);
+  });
+  return {
+    command: command,
+    config: parsed.values.config,
+    checkRequired: COMMANDS[command].checkRequired,
+    includeSecrets: parsed.values['include-secrets'] === true
+  };
+}
+
+// Runs a command other than run for the parsed command line. Resolves to the
+// exit code.
+export async function runCommand(cli) {
+  try {
+    return await COMMANDS[cli.command].run(cli);
+  } catch (e) {
+    console.log(`ERROR: ${e.message}`);
+    return 1;
+  }
+}
+
+const tomlValue = (type, value) =>
+  type === 'number' || type === 'boolean' ? value : JSON.stringify(value);
+
+// The settings currently in effect, as a config file. Unless includeSecrets,
+// secret settings are left commented out and credentials in URLs masked,
+// as in the startup log, so the output is safe to share.
+export function exportConfig(includeSecrets = false) {
+  return Object.entries(SETTINGS)
+    .filter(([setting]) => process.env[setting])
+    .map(([setting, type]) => {
+      if (!includeSecrets && isSecretSetting(setting)) {
+        return `# ${setting.toLowerCase()} is secret, see --include-secrets`;
+      }
+      const value = includeSecrets ? process.env[setting] : redactSetting(setting, process.env[setting]);
+      if (type === 'list') {
+        return `${setting.toLowerCase()} = ${JSON.stringify(value.split(',').map((v) => v.trim()))}`;
+      }
+      return `${setting.toLowerCase()} = ${tomlValue(type, value)}`;
+    })
+    .join('\n');
+}
@@ -0,0 +1,71 @@
+// Batch ping records into ClickHouse over its HTTP interface. Rows are
+// inserted as JSONEachRow. Fields the table doesn't have are skipped, so
+// the table can hold any subset of the ping record. A failed insert is
+// retried with the next batch, unless ClickHouse rejected it outright. At
+// most maxBuffered rows are held.
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+import { isRetryableStatus } from './vaqueue.mjs';
+
+const INSERT_TIMEOUT_MS = 10000;
+
+let rows = [];
+let clickhouseConfig;
+
+export const clickhouseBufferSize = () => rows.length;
+
+export function configureClickhouse({ url, table, user, password, maxBuffered }) {
+  clickhouseConfig = { url, table, user, password, maxBuffered };
+}
+
+export function addClickhouseRow(record) {
+  rows.push(record);
+  if (rows.length > clickhouseConfig.maxBuffered) {
+    rows.shift();
+    console.log(`${timestamp()} WARNING: ClickHouse buffer is full, dropped the oldest ping`);
+  }
+}
+
+async function insert(batch) {
+  const { url, table, user, password } = clickhouseConfig;
+  const query = new URLSearchParams({
+    query: `INSERT INTO ${table} FORMAT JSONEachRow`,
+    input_format_skip_unknown_fields: '1',
+    date_time_input_format: 'best_effort'
+  });
+  const headers = { 'Content-Type': 'application/x-ndjson' };
+  if (user) headers['X-ClickHouse-User'] = user;
+  if (password) headers['X-ClickHouse-Key'] = password;
+
+  const response = await fetch(`${url.replace(/\/$/, '')}/?${query}`, {
+    method: 'POST',
+    headers: headers,
+    body: batch.map((r) => JSON.stringify(r)).join('\n'),
+    signal: AbortSignal.timeout(INSERT_TIMEOUT_MS)
+  });
+  if (isRetryableStatus(response.status)) {
+    throw new Error(`HTTP ${response.status}: ${(await response.text()).slice(0, 200)}`);
+  }
+  // e.g. a bad table name or type, which a retry won't fix
+  if (!response.ok) {
+    console.log(`${timestamp()} ERROR: ClickHouse rejected ${batch.length} rows with ${response.status}, dropping them: ${(await response.text()).slice(0, 200)}`);
+  }
+}
+
+export async function runClickhouseSink(intervalMs, batchSize) {
+  let failures = 0;
+  while (true) {
+    await sleep(failures ? backoffMs(failures, intervalMs) : intervalMs);
+    while (rows.length) {
+      const batch = rows.slice(0, batchSize);
+      try {
+        await insert(batch);
+      } catch (e) {
+        failures++;
+        console.log(`${timestamp()} ERROR: ClickHouse insert of ${batch.length} rows failed (${failures} in a row): ${e.message}`);
+        break;
+      }
+      failures = 0;
+      rows = rows.slice(batch.length);
+    }
+  }
+}
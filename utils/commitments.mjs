@@ -0,0 +1,70 @@
+// Record when a single TX reaches each commitment level so we can measure
+// processed => confirmed and confirmed => finalized separately.
+import { sleep, timestamp } from './misc.mjs';
+import { COMMITMENT_LEVELS } from './deadline.mjs';
+import { commitmentReached } from './confirm.mjs';
+
+// Subscribes at every commitment level. `times` holds the milliseconds from
+// startMs until each level was reached.
+export function watchCommitmentLevels(connection, signature, startMs) {
+  const times = {};
+  const subscriptions = {};
+
+  const record = (commitment) => {
+    if (times[commitment] === undefined) times[commitment] = Date.now() - startMs;
+  };
+
+  COMMITMENT_LEVELS.forEach((commitment) => {
+    subscriptions[commitment] = connection.onSignature(
+      signature,
+      () => {
+        // Signature subscriptions are removed after the first notification
+        delete subscriptions[commitment];
+        record(commitment);
+      },
+      commitment
+    );
+  });
+
+  return {
+    times,
+
+    // Wait until the TX is finalized or timeoutMs has passed. Polls
+    // getSignatureStatuses in case a notification was missed, in which case
+    // the recorded time is an upper bound.
+    async waitForFinalized(pollMs, timeoutMs) {
+      const giveUpAt = Date.now() + Number(timeoutMs);
+      while (times.finalized === undefined && Date.now() < giveUpAt) {
+        await sleep(pollMs);
+        try {
+          const { value } = await connection.getSignatureStatuses([signature]);
+          COMMITMENT_LEVELS.forEach((commitment) => {
+            if (commitmentReached(value[0], commitment)) record(commitment);
+          });
+        } catch (e) {
+          console.log(`${timestamp()} ERROR: getSignatureStatuses failed: ${e.message}`);
+        }
+      }
+      return times;
+    },
+
+    stop() {
+      Object.values(subscriptions).forEach((id) => {
+        connection.removeSignatureListener(id);
+      });
+    }
+  };
+}
+
+// Deltas between commitment levels, undefined where a level wasn't reached.
+export function commitmentDeltas(times) {
+  const delta = (from, to) =>
+    times[from] === undefined || times[to] === undefined
+      ? undefined
+      : times[to] - times[from];
+
+  return {
+    processedToConfirmed: delta('processed', 'confirmed'),
+    confirmedToFinalized: delta('confirmed', 'finalized')
+  };
+}
@@ -0,0 +1,386 @@
+// Optional TOML config file. Every setting is still an env var; the file
+// just fills in the ones the environment (and .env) leave unset, so env vars
+// always win. Keys map to env vars by upper-casing and prefixing the
+// section, e.g.
+//
+//   sleep_ms = 15000
+//   rpc_endpoints = ["https://a.example", "https://b.example"]
+//   [influxdb]
+//   write_url = "http://localhost:8086/api/v2/write?bucket=pings"
+//
+// sets SLEEP_MS, RPC_ENDPOINTS (comma joined) and INFLUXDB_WRITE_URL. Only
+// the TOML we need is supported: comments, [sections], strings, numbers,
+// booleans and arrays of those.
+import fs from 'fs';
+import dotenv from 'dotenv';
+
+// Every setting the client reads, by type. number and boolean values are
+// checked at startup, list values may be TOML arrays.
+export const SETTINGS = {
+  CLUSTER_PROFILES: 'list',
+  RPC_ENDPOINT: 'string',
+  RPC_ENDPOINTS: 'list',
+  RPC_HEADERS: 'string',
+  PROXY_URL: 'string',
+  RPC_PROVIDERS: 'list',
+  RPC_REGIONS: 'list',
+  RPC_SEND_MODE: 'string',
+  RPC_FAILOVER_THRESHOLD: 'number',
+  RPC_FAILOVER_RESET_MS: 'number',
+  WALLET_PRIVATE_KEYPAIR: 'string',
+  WALLET_KEYPAIR_PATH: 'string',
+  WALLET_KEYPAIR_PATHS: 'list',
+  WALLET_PASSPHRASE_FILE: 'string',
+  SLEEP_MS: 'number',
+  VA_API_KEY: 'string',
+  VA_ENDPOINT: 'string',
+  VA_CLUSTER: 'string',
+  VA_QUEUE_FILE: 'string',
+  VA_QUEUE_MAX: 'number',
+  VA_QUEUE_RETRY_MS: 'number',
+  VA_BATCH_INTERVAL_MS: 'number',
+  VA_BATCH_MAX_SIZE: 'number',
+  VERBOSE_LOG: 'boolean',
+  LOG_FORMAT: 'string',
+  LOG_FILE: 'string',
+  LOG_FILE_MAX_MB: 'number',
+  LOG_FILE_MAX_FILES: 'number',
+  LOG_FILE_ROTATE_DAILY: 'boolean',
+  PINGER_NAME: 'string',
+  PINGER_REGION: 'string',
+  CRASH_WEBHOOK_URL: 'string',
+  PUSHGATEWAY_URL: 'string',
+  SENTRY_DSN: 'string',
+  SLACK_WEBHOOK_URL: 'string',
+  DISCORD_WEBHOOK_URL: 'string',
+  TELEGRAM_BOT_TOKEN: 'string',
+  TELEGRAM_CHAT_ID: 'string',
+  ALERT_MIN_INTERVAL_MS: 'number',
+  ALERT_TIMEOUT_STREAK: 'number',
+  BLOCK_HEIGHT_POLL_MS: 'number',
+  BLOCK_HEIGHT_MAX_AGE_MS: 'number',
+  SIGNATURE_STATUS_POLL_MS: 'number',
+  LATE_LANDING_GRACE_MS: 'number',
+  RESEND_INTERVAL_MS: 'number',
+  MAX_RESENDS: 'number',
+  RESEND_BACKOFF: 'string',
+  REPORT_FAILURES: 'boolean',
+  MEASURE_ALL_COMMITMENTS: 'boolean',
+  FINALIZED_TIMEOUT_MS: 'number',
+  COMMITMENT_LEVEL: 'string',
+  TX_CONFIRMATION_TIMEOUT_MS: 'number',
+  TX_CONFIRMATION_TIMEOUT_PROCESSED_MS: 'number',
+  TX_CONFIRMATION_TIMEOUT_CONFIRMED_MS: 'number',
+  TX_CONFIRMATION_TIMEOUT_FINALIZED_MS: 'number',
+  LEADER_SCHEDULE_POLL_MS: 'number',
+  LEADER_STATS_WINDOW: 'number',
+  LEADER_STATS_LOG_MS: 'number',
+  LEADER_STATS_LOG_COUNT: 'number',
+  LEADER_STATS_METRICS_COUNT: 'number',
+  SUMMARY_INTERVAL_MS: 'number',
+  LANDING_RATE_WINDOW: 'number',
+  LANDING_RATE_ALERT_THRESHOLD: 'number',
+  LANDING_RATE_ALERT_WEBHOOK_URL: 'string',
+  SLOT_MAX_AGE_MS: 'number',
+  SLOT_STALE_MS: 'number',
+  SLOT_LAG_CHECK_MS: 'number',
+  RTT_POLL_MS: 'number',
+  PRIORITY_FEE_PERCENTILE: 'number',
+  PRIORITY_FEE_POLL_MS: 'number',
+  PRIORITY_FEE_SOURCE: 'string',
+  PRIORITY_FEE_MICRO_LAMPORTS: 'number',
+  PRIORITY_FEE_ACCOUNTS: 'list',
+  PRIORITY_FEE_WINDOW_SLOTS: 'number',
+  PRIORITY_FEE_EMA_ALPHA: 'number',
+  PRIORITY_FEE_SPIKE_FACTOR: 'number',
+  PRIORITY_FEE_MIN: 'number',
+  PRIORITY_FEE_MAX: 'number',
+  PRIORITY_FEE_TARGET_PROBABILITY: 'number',
+  PRIORITY_FEE_TARGET_SLOTS: 'number',
+  PRIORITY_FEE_TARGET_WINDOW_MS: 'number',
+  PRIORITY_FEE_TARGET_MIN_SAMPLES: 'number',
+  EXPERIMENT_CU_LIMITS: 'list',
+  EXPERIMENT_CU_PRICES: 'list',
+  EXPERIMENT_TX_SIZES: 'list',
+  EXPERIMENT_COMMITMENTS: 'list',
+  EXPERIMENT_FILE: 'string',
+  ANALYZE_FORMAT: 'string',
+  ANALYZE_TABLE: 'string',
+  ANALYZE_WINDOW_HOURS: 'number',
+  RESEARCH_EXPORT_DIR: 'string',
+  RESEARCH_EXPORT_SINCE: 'string',
+  PRIORITY_FEE_LEVEL: 'string',
+  PRIORITY_FEE_API_URL: 'string',
+  COMPUTE_UNIT_LIMIT: 'number',
+  FETCH_TX_DETAILS: 'boolean',
+  BALANCE_POLL_MS: 'number',
+  LOW_BALANCE_LAMPORTS: 'number',
+  AIRDROP_BELOW_LAMPORTS: 'number',
+  AIRDROP_LAMPORTS: 'number',
+  RUNWAY_WINDOW_MS: 'number',
+  RUNWAY_ALERT_HOURS: 'number',
+  MAX_DAILY_FEE_LAMPORTS: 'number',
+  FEE_BUDGET_FILE: 'string',
+  MAX_RSS_MB: 'number',
+  OTLP_ENDPOINT: 'string',
+  METRICS_BACKEND: 'string',
+  STATSD_ADDRESS: 'string',
+  REPORTERS: 'list',
+  INFLUXDB_WRITE_URL: 'string',
+  INFLUXDB_TOKEN: 'string',
+  PING_WEBHOOK_URL: 'string',
+  PING_WEBHOOK_HEADERS: 'string',
+  PING_WEBHOOK_TEMPLATE: 'string',
+  PING_LOG_FILE: 'string',
+  PING_CSV_DIR: 'string',
+  PING_SQLITE_DB: 'string',
+  CLICKHOUSE_URL: 'string',
+  CLICKHOUSE_TABLE: 'string',
+  CLICKHOUSE_USER: 'string',
+  CLICKHOUSE_PASSWORD: 'string',
+  CLICKHOUSE_INTERVAL_MS: 'number',
+  CLICKHOUSE_BATCH_SIZE: 'number',
+  KAFKA_REST_URL: 'string',
+  KAFKA_TOPIC: 'string',
+  KAFKA_INTERVAL_MS: 'number',
+  NATS_URL: 'string',
+  NATS_SUBJECT_PREFIX: 'string',
+  STATUS_PORT: 'number',
+  STATUS_BIND_ADDRESS: 'string',
+  RECENT_PINGS_MAX: 'number'
+};
+
+// Each entry is a setting, or a list of settings one of which is required
+export const REQUIRED_SETTINGS = [
+  'RPC_ENDPOINT',
+  ['WALLET_KEYPAIR_PATHS', 'WALLET_KEYPAIR_PATH', 'WALLET_PRIVATE_KEYPAIR'],
+  'SLEEP_MS',
+  'VA_API_KEY'
+];
+
+// Settings that must never be logged. URLs in other settings have their
+// password and query string values masked too, since RPC providers often put
+// API keys there.
+const SECRET_SETTINGS = [
+  'WALLET_PRIVATE_KEYPAIR',
+  'VA_API_KEY',
+  'SENTRY_DSN',
+  'CRASH_WEBHOOK_URL',
+  'SLACK_WEBHOOK_URL',
+  'DISCORD_WEBHOOK_URL',
+  'TELEGRAM_BOT_TOKEN',
+  'LANDING_RATE_ALERT_WEBHOOK_URL',
+  'INFLUXDB_TOKEN',
+  'PING_WEBHOOK_HEADERS',
+  'RPC_HEADERS',
+  'CLICKHOUSE_PASSWORD'
+];
+
+function redactUrl(text) {
+  return text.replace(/\b(\w+:\/\/)([^\s,/?#]*@)?([^\s,?#]*)(\?[^\s,#]*)?/g, (match, scheme, userinfo, rest, query) => {
+    const user = userinfo ? userinfo.replace(/:.*@$/, ':***@') : '';
+    const params = query ? query.replace(/=[^&]*/g, '=***') : '';
+    return scheme + user + rest + params;
+  });
+}
+
+export const isSecretSetting = (name) => SECRET_SETTINGS.includes(name);
+
+// A setting's value as it is safe to show: *** for secret settings, URL
+// credentials masked for the rest
+export const redactSetting = (name, value) => isSecretSetting(name) ? '***' : redactUrl(value);
+
+// The settings in `env`, one NAME=value per line with secrets redacted
+export function describeSettings(env) {
+  return Object.keys(SETTINGS)
+    .filter((name) => env[name])
+    .map((name) => `${name}=${redactSetting(name, env[name])}`)
+    .join('\n');
+}
+
+function parseValue(text, where) {
+  if (text.startsWith('"')) return JSON.parse(text);
+  if (text.startsWith("'")) return text.slice(1, -1);
+  if (text === 'true' || text === 'false') return text === 'true';
+  if (text.startsWith('[')) {
+    const inner = text.slice(1, -1).trim();
+    return inner === ''
+      ? []
+      : splitTopLevel(inner).map((item) => parseValue(item.trim(), where)).filter((v) => v !== '');
+  }
+  const number = Number(text.replace(/_/g, ''));
+  if (text !== '' && !Number.isNaN(number)) return number;
+  throw new Error(`${where}: can't parse value ${text}`);
+}
+
+// Split "a, 'b,c', 3" on the commas outside quotes
+function splitTopLevel(text) {
+  const items = [];
+  let current = '';
+  let quote;
+  for (const c of text) {
+    if (quote) {
+      if (c === quote && !current.endsWith('\\')) quote = undefined;
+    } else if (c === '"' || c === "'") {
+      quote = c;
+    } else if (c === ',') {
+      items.push(current);
+      current = '';
+      continue;
+    }
+    current += c;
+  }
+  if (current.trim()) items.push(current);
+  return items;
+}
+
+function stripComment(line) {
+  let quote;
+  for (let i = 0; i < line.length; i++) {
+    const c = line[i];
+    if (quote) {
+      if (c === quote && line[i - 1] !== '\\') quote = undefined;
+    } else if (c === '"' || c === "'") {
+      quote = c;
+    } else if (c === '#') {
+      return line.slice(0, i);
+    }
+  }
+  return line;
+}
+
+// Returns { section: { key: value } } for the TOML file at `path`. Keys
+// before the first section are under ''.
+export function parseTomlSections(path) {
+  const sections = { '': {} };
+  let section = '';
+  let pending = '';
+  fs.readFileSync(path, 'utf8').split('\n').forEach((raw, index) => {
+    const where = `${path}:${index + 1}`;
+    const line = (pending + stripComment(raw)).trim();
+    pending = '';
+    if (!line) return;
+
+    const sectionMatch = line.match(/^\[([\w.-]+)\]$/);
+    if (sectionMatch) {
+      section = sectionMatch[1];
+      sections[section] = sections[section] || {};
+      return;
+    }
+    const match = line.match(/^([\w-]+)\s*=\s*(.*)$/);
+    if (!match) throw new Error(`${where}: expected key = value`);
+    // Arrays may span lines
+    if (match[2].startsWith('[') && !match[2].endsWith(']')) {
+      pending = line + ' ';
+      return;
+    }
+    sections[section][match[1]] = parseValue(match[2], where);
+  });
+  return sections;
+}
+
+// Returns { ENV_NAME: value } for the file at `path`.
+export function parseConfigFile(path) {
+  const values = {};
+  Object.entries(parseTomlSections(path)).forEach(([section, entries]) => {
+    const prefix = section ? section.replace(/[.-]/g, '_') + '_' : '';
+    Object.entries(entries).forEach(([key, value]) => {
+      values[(prefix + key).replace(/-/g, '_').toUpperCase()] = value;
+    });
+  });
+  return values;
+}
+
+// Fill `env` from the file at `path` under any values already set, or over
+// them with `override`.
+function applyConfigFile(env, path, problems, override = false) {
+  const values = parseConfigFile(path);
+  Object.entries(values).forEach(([name, value]) => {
+    if (!SETTINGS[name]) {
+      problems.push(`unknown setting ${name} in ${path}`);
+      return;
+    }
+    // Empty env vars, e.g. from a copied .env.sample, count as unset
+    if (env[name] && !override) return;
+    env[name] = Array.isArray(value) ? value.join(',') : String(value);
+  });
+}
+
+function checkSettings(env, checkRequired, problems) {
+  // With CLUSTER_PROFILES the required settings are checked per profile
+  REQUIRED_SETTINGS
+    .map((names) => [].concat(names))
+    .filter((names) => checkRequired && !env.CLUSTER_PROFILES && !names.some((name) => env[name]))
+    .forEach((names) => problems.push(`missing required setting ${names.join(' or ')}`));
+  Object.entries(SETTINGS).forEach(([name, type]) => {
+    const value = env[name];
+    if (value === undefined || value === '') return;
+    if (type === 'number' && Number.isNaN(Number(value))) {
+      problems.push(`${name} must be a number, got ${value}`);
+    } else if (type === 'boolean' && !['true', 'false'].includes(value)) {
+      problems.push(`${name} must be true or false, got ${value}`);
+    }
+  });
+  if (problems.length) {
+    throw new Error(`Invalid configuration:\n  ${problems.join('\n  ')}`);
+  }
+}
+
+// Load `path` into process.env under any env vars already set, then check
+// every setting. Throws listing all problems at once.
+export function loadConfig(path, checkRequired = true) {
+  const problems = [];
+  if (path) applyConfigFile(process.env, path, problems);
+  checkSettings(process.env, checkRequired, problems);
+}
+
+// The environment for a CLUSTER_PROFILES entry: the current settings with
+// the profile file's values on top. Throws like loadConfig.
+export function profileEnv(path) {
+  const env = { ...process.env, CLUSTER_PROFILES: '', CONFIG_FILE: '' };
+  const problems = [];
+  applyConfigFile(env, path, problems, true);
+  checkSettings(env, true, problems);
+  return env;
+}
+
+// The environment before .env and the config file were applied, i.e. real
+// env vars and command line flags
+let baseEnv = {};
+// Settings removed from process.env once read, see scrubSetting
+const scrubbed = new Set();
+
+export function snapshotEnv() {
+  baseEnv = { ...process.env };
+}
+
+// Re-read .env and the config file on top of the snapshot and, if the result
+// checks out, update process.env to match. Throws like loadConfig.
+export function reloadConfig(path) {
+  const env = { ...baseEnv };
+  if (fs.existsSync('.env')) {
+    Object.entries(dotenv.parse(fs.readFileSync('.env'))).forEach(([name, value]) => {
+      if (env[name] === undefined) env[name] = value;
+    });
+  }
+  const problems = [];
+  if (path) applyConfigFile(env, path, problems);
+  checkSettings(env, true, problems);
+
+  Object.keys(SETTINGS).forEach((name) => {
+    if (scrubbed.has(name)) return;
+    if (env[name] === undefined) {
+      delete process.env[name];
+    } else {
+      process.env[name] = env[name];
+    }
+  });
+}
+
+// Remove a secret from process.env once it has been read, so it isn't
+// inherited by child processes (sqlite3, systemd-notify) or dumped with the
+// environment. Reloads leave it out too.
+export function scrubSetting(name) {
+  scrubbed.add(name);
+  delete process.env[name];
+}
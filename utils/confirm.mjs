@@ -0,0 +1,90 @@
+// Confirm a signature with the web3.js websocket subscription, backed up by
+// polling getSignatureStatuses. The websocket occasionally misses a
+// notification and we never want to report a landed TX as timed out.
+import { sleep, timestamp } from './misc.mjs';
+import { COMMITMENT_LEVELS, deadline } from './deadline.mjs';
+import { rateLimitedDelay, noteRateLimit } from './ratelimit.mjs';
+
+// Has the signature status reached (or passed) the requested commitment?
+export function commitmentReached(status, commitment) {
+  if (!status || !status.confirmationStatus) return false;
+  return COMMITMENT_LEVELS.indexOf(status.confirmationStatus) >=
+    COMMITMENT_LEVELS.indexOf(commitment);
+}
+
+// Look up a single signature. Returns the status once it has reached the
+// commitment level, otherwise null.
+export async function fetchSignatureStatus(connection, signature, commitment) {
+  const { value } = await connection.getSignatureStatuses(
+    [signature],
+    { searchTransactionHistory: true }
+  );
+  return commitmentReached(value[0], commitment) ? value[0] : null;
+}
+
+// Resolves like connection.confirmTransaction, i.e. with { context, value }
+// where value.err is set for TXs that landed but failed. Throws the web3.js
+// expiry error if the blockhash expires before the TX is seen, or a
+// TransactionExpiredTimeoutError once deadlineMs (if set) has passed.
+export async function confirmSignature(
+  connection,
+  signature,
+  latestBlockhash,
+  commitment,
+  pollMs,
+  deadlineMs
+) {
+  let done = false;
+
+  const poll = async () => {
+    while (!done) {
+      await sleep(rateLimitedDelay(pollMs));
+      if (done) break;
+      try {
+        const status = await fetchSignatureStatus(connection, signature, commitment);
+        if (status) return { context: { slot: status.slot }, value: { err: status.err } };
+      } catch (e) {
+        console.log(`${timestamp()} ERROR: getSignatureStatuses failed: ${e.message}`);
+        noteRateLimit('confirm', e);
+      }
+    }
+    // The subscription won the race. Never settle.
+    return new Promise(() => {});
+  };
+
+  const timeout = deadline(deadlineMs, signature);
+  try {
+    return await Promise.race([
+      connection.confirmTransaction(
+        { signature, ...latestBlockhash },
+        commitment
+      ),
+      poll(),
+      timeout.promise
+    ]);
+  } catch (e) {
+    // One last look before giving up in case the TX landed after our most
+    // recent poll.
+    if (
+      e.name === 'TransactionExpiredBlockheightExceededError' ||
+      e.name === 'TransactionExpiredTimeoutError'
+    ) {
+      const status = await fetchSignatureStatus(connection, signature, commitment)
+        .catch(() => null);
+      if (status) return { context: { slot: status.slot }, value: { err: status.err } };
+    }
+    throw e;
+  } finally {
+    done = true;
+    timeout.clear();
+  }
+}
+
+// The slot a confirmed TX landed in.
+export async function fetchLandedSlot(connection, signature) {
+  const { value } = await connection.getSignatureStatuses(
+    [signature],
+    { searchTransactionHistory: true }
+  );
+  return value[0] ? value[0].slot : undefined;
+}
@@ -0,0 +1,70 @@
+// Make crashes loud. Uncaught exceptions and unhandled rejections are logged
+// and reported to an optional webhook, Prometheus pushgateway and Sentry
+// before the process exits.
+import { timestamp } from './misc.mjs';
+import { captureError } from './sentry.mjs';
+
+// Don't let a slow sink hold up the exit
+const REPORT_TIMEOUT_MS = 5000;
+
+let crashConfig = {};
+
+function postWithTimeout(url, body, contentType) {
+  return fetch(url, {
+    method: 'POST',
+    headers: { 'Content-Type': contentType },
+    body: body,
+    signal: AbortSignal.timeout(REPORT_TIMEOUT_MS)
+  });
+}
+
+// Report a fatal error to the configured sinks. Resolves once every sink has
+// answered or timed out.
+export async function reportCrash(error) {
+  const message = error && error.stack ? error.stack : String(error);
+  console.log(`${timestamp()} FATAL: ${message}`);
+
+  const { webhookUrl, pushgatewayUrl, pingerName } = crashConfig;
+  const reports = [];
+  if (webhookUrl) {
+    reports.push(postWithTimeout(
+      webhookUrl,
+      JSON.stringify({
+        event: 'crash',
+        pinger_name: pingerName,
+        message: message,
+        time: timestamp()
+      }),
+      'application/json'
+    ));
+  }
+  // The process exits after every crash and each push replaces the group, so
+  // only the time of the last crash is pushed. Alert on it changing, e.g.
+  // changes(ping_thing_last_crash_timestamp_seconds[1h]) > 0.
+  if (pushgatewayUrl) {
+    reports.push(postWithTimeout(
+      `${pushgatewayUrl.replace(/\/$/, '')}/metrics/job/ping_thing_client/pinger_name/${encodeURIComponent(pingerName)}`,
+      '# TYPE ping_thing_last_crash_timestamp_seconds gauge\n' +
+        `ping_thing_last_crash_timestamp_seconds ${Math.floor(Date.now() / 1000)}\n`,
+      'text/plain'
+    ));
+  }
+
+  reports.push(captureError(error, 'crash', {}, 'fatal'));
+
+  const results = await Promise.allSettled(reports);
+  results
+    .filter((r) => r.status === 'rejected')
+    .forEach((r) => console.log(`${timestamp()} ERROR: Unable to report crash: ${r.reason.message}`));
+}
+
+export function installCrashHandlers(config) {
+  crashConfig = config;
+
+  const crash = async (error) => {
+    await reportCrash(error);
+    process.exit(1);
+  };
+  process.on('uncaughtException', crash);
+  process.on('unhandledRejection', crash);
+}
@@ -0,0 +1,56 @@
+// Write ping records to daily CSV files (pings-YYYY-MM-DD.csv in dir) for
+// analysis with pandas or DuckDB. A new file starts with a header row.
+import fs from 'fs';
+import path from 'path';
+import { timestamp } from './misc.mjs';
+
+export const CSV_COLUMNS = [
+  'sent_at',
+  'pinger_name',
+  'region',
+  'tx_signature',
+  'transaction_type',
+  'success',
+  'reason',
+  'error_category',
+  'commitment_level',
+  'time',
+  'resends',
+  'slot_sent',
+  'slot_landed',
+  'leader_sent',
+  'leader_landed',
+  'slots_to_next_leader',
+  'priority_fee_micro_lamports',
+  'priority_fee_percentile',
+  'compute_unit_limit',
+  'tx_size',
+  'experiment',
+  'variant',
+  'compute_units_consumed',
+  'fee_lamports',
+  'tx_index',
+  'rpc_rtt_ms',
+  'send_ms',
+  'inclusion_ms',
+  'processed_time',
+  'confirmed_time',
+  'finalized_time'
+];
+
+function csvValue(value) {
+  if (value === undefined || value === null) return '';
+  const text = String(value);
+  return /[",\n]/.test(text) ? `"${text.replace(/"/g, '""')}"` : text;
+}
+
+export function appendPingCsv(dir, record) {
+  const file = path.join(dir, `pings-${record.sent_at.slice(0, 10)}.csv`);
+  try {
+    const header = fs.existsSync(file) ? '' : CSV_COLUMNS.join(',') + '\n';
+    const row = CSV_COLUMNS.map((c) => csvValue(record[c])).join(',');
+    fs.appendFileSync(file, header + row + '\n');
+  } catch (e) {
+    console.log(`${timestamp()} ERROR: Unable to write ping CSV ${file}: ${e.message}`);
+  }
+}
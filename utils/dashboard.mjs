@@ -0,0 +1,98 @@
+// A single page dashboard on / that polls /pings/stats, /pings/recent and
+// /healthz every few seconds. No external assets, so it works offline.
+import { addRoute } from './server.mjs';
+
+const PAGE = `<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Ping Thing</title>
+<style>
+  body { font-family: sans-serif; margin: 2em; color: #222; }
+  .tiles { display: flex; flex-wrap: wrap; gap: 1em; }
+  .tile { border: 1px solid #ccc; border-radius: 4px; padding: 0.8em 1.2em; min-width: 9em; }
+  .tile .value { font-size: 1.6em; font-weight: bold; }
+  .tile .label { color: #666; font-size: 0.85em; }
+  .bad { color: #c0392b; }
+  .good { color: #27ae60; }
+  table { border-collapse: collapse; margin-top: 1em; font-size: 0.9em; }
+  td, th { border-bottom: 1px solid #eee; padding: 0.3em 0.8em; text-align: left; }
+  svg { border: 1px solid #eee; margin-top: 1em; }
+</style>
+</head>
+<body>
+<h1>Ping Thing <span id="pinger"></span></h1>
+<div class="tiles" id="tiles"></div>
+<svg id="chart" width="800" height="160"></svg>
+<h2>Watchers</h2>
+<table id="watchers"></table>
+<h2>Recent pings</h2>
+<table id="recent"></table>
+<script>
+const fmt = (v, unit) => v === null || v === undefined ? 'n/a' : v + (unit || '');
+const tile = (label, value, cls) =>
+  '<div class="tile"><div class="value ' + (cls || '') + '">' + value + '</div><div class="label">' + label + '</div></div>';
+
+async function refresh() {
+  const [stats, recent, health] = await Promise.all([
+    fetch('/pings/stats?window_ms=3600000').then((r) => r.json()),
+    fetch('/pings/recent?limit=100').then((r) => r.json()),
+    fetch('/healthz').then((r) => r.json())
+  ]);
+  const latest = recent[0] || {};
+  document.getElementById('pinger').textContent = latest.pinger_name ? '(' + latest.pinger_name + ')' : '';
+  const rate = stats.landing_rate === null ? 'n/a' : (stats.landing_rate * 100).toFixed(1) + '%';
+  document.getElementById('tiles').innerHTML = [
+    tile('landing rate (1h)', rate, stats.landing_rate !== null && stats.landing_rate < 0.9 ? 'bad' : 'good'),
+    tile('p50 latency (1h)', fmt(stats.latency_ms.p50, 'ms')),
+    tile('p90 latency (1h)', fmt(stats.latency_ms.p90, 'ms')),
+    tile('p99 latency (1h)', fmt(stats.latency_ms.p99, 'ms')),
+    tile('median slot latency (1h)', fmt(stats.median_slot_latency)),
+    tile('current priority fee', fmt(latest.priority_fee_micro_lamports, ' µL/CU')),
+    tile('pings (1h)', stats.pings),
+    tile('ready', health.ready ? 'yes' : 'no', health.ready ? 'good' : 'bad')
+  ].join('');
+
+  const points = recent.slice().reverse();
+  const max = Math.max(1, ...points.map((p) => p.success ? p.time : 0));
+  document.getElementById('chart').innerHTML = points.map((p, i) => {
+    const x = 4 + i * 7.9;
+    const h = p.success ? Math.max(2, (p.time / max) * 140) : 140;
+    return '<rect x="' + x + '" y="' + (150 - h) + '" width="6" height="' + h + '" fill="' +
+      (p.success ? '#3498db' : '#e74c3c') + '"><title>' + (p.success ? p.time + 'ms' : p.reason) + '</title></rect>';
+  }).join('');
+
+  const restarts = health.watcher_restarts || {};
+  document.getElementById('watchers').innerHTML =
+    '<tr><th>watcher</th><th>fresh</th><th>age</th><th>restarts</th></tr>' +
+    Object.entries(health.watchers).map(([name, w]) =>
+      '<tr><td>' + name + '</td><td class="' + (w.fresh ? 'good' : 'bad') + '">' + w.fresh + '</td><td>' + fmt(w.age_ms, 'ms') + '</td><td>' + fmt(restarts[name]) + '</td></tr>'
+    ).join('') +
+    Object.entries(restarts).filter(([name]) => !health.watchers[name]).map(([name, n]) =>
+      '<tr><td>' + name + '</td><td></td><td></td><td>' + n + '</td></tr>'
+    ).join('');
+
+  document.getElementById('recent').innerHTML =
+    '<tr><th>sent</th><th>signature</th><th>result</th><th>latency</th><th>slots</th><th>fee</th></tr>' +
+    recent.slice(0, 20).map((p) =>
+      '<tr><td>' + p.sent_at + '</td><td>' + (p.tx_signature || '').slice(0, 16) + '…</td><td class="' + (p.success ? 'good' : 'bad') + '">' +
+      (p.success ? 'landed' : p.reason) + '</td><td>' + fmt(p.time, 'ms') + '</td><td>' +
+      (p.slot_landed !== undefined && p.slot_sent !== undefined ? p.slot_landed - p.slot_sent : 'n/a') + '</td><td>' +
+      fmt(p.priority_fee_micro_lamports) + '</td></tr>'
+    ).join('');
+}
+
+refresh();
+setInterval(refresh, 5000);
+</script>
+</body>
+</html>
+`;
+
+export function addDashboardRoute() {
+  addRoute('/', () => ({
+    status: 200,
+    contentType: 'text/html; charset=utf-8',
+    body: PAGE
+  }));
+}
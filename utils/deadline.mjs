@@ -0,0 +1,34 @@
+// Confirmation deadlines. TX_CONFIRMATION_TIMEOUT_MS applies to every
+// commitment level and can be overridden per level with e.g.
+// TX_CONFIRMATION_TIMEOUT_FINALIZED_MS. Without a deadline we wait until the
+// blockhash expires.
+export const COMMITMENT_LEVELS = ['processed', 'confirmed', 'finalized'];
+
+export function confirmationDeadlines(env) {
+  const deadlines = {};
+  COMMITMENT_LEVELS.forEach((commitment) => {
+    const value = env[`TX_CONFIRMATION_TIMEOUT_${commitment.toUpperCase()}_MS`] ||
+      env.TX_CONFIRMATION_TIMEOUT_MS;
+    deadlines[commitment] = value ? Number(value) : undefined;
+  });
+  return deadlines;
+}
+
+// { promise, clear }: promise rejects after `ms` with an error named like
+// the web3.js timeout error so callers can treat both the same way, and never
+// settles if ms is undefined. Call clear() once the race is over so the timer
+// doesn't outlive it.
+export function deadline(ms, signature) {
+  let timer;
+  const promise = new Promise((_resolve, reject) => {
+    if (ms === undefined) return;
+    timer = setTimeout(() => {
+      const error = new Error(
+        `Transaction ${signature} was not confirmed in ${ms}ms`
+      );
+      error.name = 'TransactionExpiredTimeoutError';
+      reject(error);
+    }, ms);
+  });
+  return { promise, clear: () => clearTimeout(timer) };
+}
@@ -0,0 +1,115 @@
+// dry-run: check everything a new pinger needs without sending a ping, and
+// print a pass/fail line per check.
+import web3 from '@solana/web3.js';
+import bs58 from 'bs58';
+import { buildPingTransaction } from './transaction.mjs';
+import { priorityFeeProviderFromEnv } from './feeproviders.mjs';
+import { endpointLabel } from './fanout.mjs';
+import { loadWallets } from './wallet.mjs';
+import { rpcConnection } from './rpcheaders.mjs';
+
+const CHECK_TIMEOUT_MS = 10000;
+
+async function withTimeout(promise) {
+  let timer;
+  try {
+    return await Promise.race([
+      promise,
+      new Promise((_resolve, reject) => {
+        timer = setTimeout(() => reject(new Error(`no answer in ${CHECK_TIMEOUT_MS}ms`)), CHECK_TIMEOUT_MS);
+      })
+    ]);
+  } finally {
+    clearTimeout(timer);
+  }
+}
+
+// Resolves to the exit code: 0 if every check passed.
+export async function dryRun(env) {
+  const results = [];
+  const check = async (name, run) => {
+    const start = Date.now();
+    try {
+      const detail = await withTimeout(run());
+      results.push(true);
+      console.log(`PASS ${name}: ${detail} (${Date.now() - start}ms)`);
+    } catch (e) {
+      results.push(false);
+      console.log(`FAIL ${name}: ${e.message}`);
+    }
+  };
+
+  let wallets = [];
+  await check('keypair', async () => {
+    wallets = await loadWallets(env);
+    return wallets.map(({ keypair }) => keypair.publicKey.toBase58()).join(', ');
+  });
+  const keypair = wallets.length ? wallets[0].keypair : undefined;
+
+  const endpoints = env.RPC_ENDPOINTS
+    ? env.RPC_ENDPOINTS.split(',').map((e) => e.trim()).filter((e) => e)
+    : [env.RPC_ENDPOINT];
+  for (const endpoint of endpoints) {
+    await check(`rpc ${endpointLabel(endpoint)}`, async () => {
+      const version = await rpcConnection(endpoint, undefined, env).getVersion();
+      return `solana-core ${version['solana-core']}`;
+    });
+  }
+
+  const connection = rpcConnection(env.RPC_ENDPOINT, undefined, env);
+  const commitment = env.COMMITMENT_LEVEL || 'confirmed';
+  await check('slot', async () => `slot ${await connection.getSlot(commitment)}`);
+
+  let latestBlockhash;
+  await check('blockhash', async () => {
+    latestBlockhash = await connection.getLatestBlockhash(commitment);
+    return `${latestBlockhash.blockhash} (valid until block height ${latestBlockhash.lastValidBlockHeight})`;
+  });
+
+  for (const wallet of wallets) {
+    await check(wallets.length > 1 ? `balance ${wallet.label}` : 'balance', async () => {
+      const lamports = await connection.getBalance(wallet.keypair.publicKey);
+      const low = Number(env.LOW_BALANCE_LAMPORTS || 10000000);
+      if (lamports < low) throw new Error(`${lamports} lamports is below LOW_BALANCE_LAMPORTS (${low})`);
+      return `${lamports} lamports`;
+    });
+  }
+
+  let feeProvider;
+  await check('priority fee source', async () => {
+    feeProvider = priorityFeeProviderFromEnv(env, [
+      ...wallets.map((w) => w.keypair.publicKey.toBase58()),
+      web3.SystemProgram.programId.toBase58()
+    ]);
+    return feeProvider ? feeProvider.name : 'none, pings go without a priority fee';
+  });
+  if (feeProvider) {
+    await check('priority fees', async () => {
+      const { fee } = await feeProvider.fetch(Number(env.PRIORITY_FEE_PERCENTILE));
+      return `${feeProvider.name} estimate ${fee} micro-lamports per CU`;
+    });
+  }
+
+  if (keypair && latestBlockhash) {
+    await check('sign', async () => {
+      const tx = buildPingTransaction(
+        keypair,
+        feeProvider ? 0 : undefined,
+        Number(env.COMPUTE_UNIT_LIMIT || 1000)
+      );
+      tx.recentBlockhash = latestBlockhash.blockhash;
+      tx.sign(keypair);
+      return `${bs58.encode(tx.signature)}, ${tx.serialize().length} bytes, not sent`;
+    });
+  }
+
+  const vaEndpoint = env.VA_ENDPOINT || 'https://www.validators.app';
+  await check('validators.app', async () => {
+    const response = await fetch(vaEndpoint, { method: 'HEAD' });
+    return `${endpointLabel(vaEndpoint)} answered ${response.status}`;
+  });
+
+  const failed = results.filter((ok) => !ok).length;
+  console.log(failed ? `${failed} of ${results.length} checks failed` : `All ${results.length} checks passed`);
+  return failed ? 1 : 0;
+}
@@ -0,0 +1,44 @@
+// Sort the ways a ping can fail into a fixed set of categories, counted by
+// ping_thing_ping_errors_total and stored with each reported ping as
+// error_category:
+//
+//   blockhash_not_found  the RPC node didn't know our blockhash
+//   insufficient_funds   the wallet couldn't pay, at preflight or on chain
+//   preflight_error      simulation failed for another reason
+//   send_error           sendTransaction failed on every endpoint
+//   on_chain_error       the TX landed but failed
+//   timeout              not confirmed in time (reason says expired or dropped)
+//   stream_loss          the websocket dropped while we waited for confirmation
+//   other                anything else
+
+export const ERROR_CATEGORIES = [
+  'blockhash_not_found',
+  'insufficient_funds',
+  'preflight_error',
+  'send_error',
+  'on_chain_error',
+  'timeout',
+  'stream_loss',
+  'other'
+];
+
+const INSUFFICIENT_FUNDS = /insufficient funds|InsufficientFunds|no record of a prior credit/i;
+const STREAM_LOSS = /websocket|socket hang up|connection closed|ECONNRESET/i;
+
+// sent says whether any endpoint accepted the TX
+export function classifyPingError(e, sent) {
+  const message = e.message || '';
+  if (message.includes('Blockhash not found')) return 'blockhash_not_found';
+  if (INSUFFICIENT_FUNDS.test(message)) return 'insufficient_funds';
+  if (message.includes('Transaction simulation failed')) return 'preflight_error';
+  if (!sent) return 'send_error';
+  if (e.name === 'TransactionFailedError') return 'on_chain_error';
+  if (
+    e.name === 'TransactionExpiredBlockheightExceededError' ||
+    e.name === 'TransactionExpiredTimeoutError'
+  ) {
+    return 'timeout';
+  }
+  if (STREAM_LOSS.test(message)) return 'stream_loss';
+  return 'other';
+}
@@ -0,0 +1,36 @@
+// Ping lifecycle events for streaming sinks: sent, resent, confirmed,
+// timed_out, failed and, once everything is known, result. A timed out ping
+// later gets late_landed or dropped. Each event is
+// { type, time, signature, pinger_name, ...fields }. type, time and
+// signature can't be overridden by fields.
+import { timestamp } from './misc.mjs';
+
+const sinks = [];
+let eventDefaults = {};
+
+export function addEventSink(sink) {
+  sinks.push(sink);
+}
+
+// Fields added to every event, e.g. the pinger name
+export function setEventDefaults(fields) {
+  eventDefaults = fields;
+}
+
+export function emitPingEvent(type, signature, fields = {}) {
+  if (sinks.length === 0) return;
+  const event = {
+    ...eventDefaults,
+    ...fields,
+    type: type,
+    time: new Date().toISOString(),
+    signature: signature
+  };
+  sinks.forEach((sink) => {
+    try {
+      sink(event);
+    } catch (e) {
+      console.log(`${timestamp()} ERROR: Ping event sink failed: ${e.message}`);
+    }
+  });
+}
@@ -0,0 +1,153 @@
+// Experiments. Instead of pinging with fixed parameters, each ping takes the
+// next cell of an experiment and is tagged with it, so the effect of each
+// parameter on landing can be compared in one process. A cell is either a
+// point on a grid of the EXPERIMENT_* lists, visited round-robin, or one of
+// the named variants in EXPERIMENT_FILE.
+import { PACKET_DATA_SIZE, TRANSACTION_TYPES } from './transaction.mjs';
+import { COMMITMENT_LEVELS } from './deadline.mjs';
+import { parseTomlSections } from './config.mjs';
+
+// Parameters that can be swept. Each is a list setting of whole numbers from
+// min to max, or of the given values.
+export const EXPERIMENT_SETTINGS = {
+  compute_unit_limit: { setting: 'EXPERIMENT_CU_LIMITS', min: 1, max: 1400000 },
+  compute_unit_price: { setting: 'EXPERIMENT_CU_PRICES', min: 0, max: Number.MAX_SAFE_INTEGER },
+  tx_size: { setting: 'EXPERIMENT_TX_SIZES', min: 0, max: PACKET_DATA_SIZE },
+  commitment_level: { setting: 'EXPERIMENT_COMMITMENTS', values: COMMITMENT_LEVELS }
+};
+
+// Variants can also set these
+const VARIANT_PARAMETERS = {
+  ...EXPERIMENT_SETTINGS,
+  transaction_type: { values: TRANSACTION_TYPES },
+  // An RPC_ENDPOINTS label, provider or region to send through
+  send_via: {}
+};
+
+// How variants are interleaved: in turn, each `weight` times per round, or
+// picked at random in proportion to `weight`
+const SCHEDULES = ['interleave', 'random'];
+
+// Throws unless value is allowed for the parameter, see EXPERIMENT_SETTINGS
+function checkValue({ min, max, values }, value, where) {
+  if (values) {
+    if (!values.includes(value)) throw new Error(`${where} must be one of ${values.join(', ')}`);
+  } else if (min !== undefined) {
+    if (!Number.isInteger(value) || value < min || value > max) {
+      throw new Error(`${where} must be a whole number from ${min} to ${max}`);
+    }
+  } else if (typeof value !== 'string' || !value) {
+    throw new Error(`${where} must be a string`);
+  }
+}
+
+// dimensions is { name: [values] }. Returns { size, cells, next() } where
+// next() returns a cell, e.g. { compute_unit_limit: 300, compute_unit_price: 0 }.
+export function createExperiment(dimensions) {
+  const cells = Object.entries(dimensions).reduce(
+    (grid, [name, values]) =>
+      grid.flatMap((cell) => values.map((value) => ({ ...cell, [name]: value }))),
+    [{}]
+  );
+  let index = 0;
+  return {
+    size: cells.length,
+    cells: cells,
+    next: () => cells[index++ % cells.length]
+  };
+}
+
+// variants is [{ variant, weight, ...parameters }]. Returns the same as
+// createExperiment, with each cell also naming its variant.
+export function createVariantExperiment(variants, schedule) {
+  const cells = variants.map(({ weight, ...cell }) => cell);
+  const weights = variants.map(({ weight }) => weight);
+  if (schedule === 'random') {
+    const total = weights.reduce((a, b) => a + b, 0);
+    return {
+      size: cells.length,
+      cells: cells,
+      next: () => {
+        let pick = Math.random() * total;
+        return cells.find((_cell, i) => (pick -= weights[i]) < 0) || cells[cells.length - 1];
+      }
+    };
+  }
+  // One round gives every variant its weight in turns, spread out
+  const round = [];
+  for (let turn = 0; turn < Math.max(...weights); turn++) {
+    cells.forEach((cell, i) => {
+      if (weights[i] > turn) round.push(cell);
+    });
+  }
+  let index = 0;
+  return {
+    size: cells.length,
+    cells: cells,
+    next: () => round[index++ % round.length]
+  };
+}
+
+// How a cell's parameters are stored with the ping, e.g.
+// compute_unit_limit=300,compute_unit_price=0
+export const experimentLabel = (cell) =>
+  Object.entries(cell)
+    .filter(([name]) => name !== 'variant')
+    .map(([name, value]) => `${name}=${value}`)
+    .join(',');
+
+// The variants in an experiment file:
+//
+//   schedule = "interleave"   # or "random"
+//
+//   [variant.cheap]
+//   compute_unit_price = 0
+//
+//   [variant.priority]
+//   compute_unit_price = 10000
+//   compute_unit_limit = 1000
+//   weight = 2
+//
+// Variants can set any of the EXPERIMENT_* parameters by name, plus
+// transaction_type and send_via.
+export function loadExperimentFile(path) {
+  const sections = parseTomlSections(path);
+  const schedule = sections[''].schedule || 'interleave';
+  if (!SCHEDULES.includes(schedule)) {
+    throw new Error(`${path}: schedule must be one of ${SCHEDULES.join(', ')}`);
+  }
+  const variants = Object.entries(sections)
+    .filter(([section]) => section.startsWith('variant.'))
+    .map(([section, { weight = 1, ...parameters }]) => {
+      const variant = section.slice('variant.'.length);
+      if (!(weight > 0)) throw new Error(`${path}: ${section} weight must be above 0`);
+      Object.entries(parameters).forEach(([name, value]) => {
+        if (!VARIANT_PARAMETERS[name]) throw new Error(`${path}: unknown parameter ${name} in ${section}`);
+        checkValue(VARIANT_PARAMETERS[name], value, `${path}: ${section} ${name}`);
+      });
+      return { variant, weight, ...parameters };
+    });
+  if (!variants.length) throw new Error(`${path}: no [variant.<name>] sections`);
+  return createVariantExperiment(variants, schedule);
+}
+
+// The experiment from EXPERIMENT_FILE or the EXPERIMENT_* lists, or
+// undefined if none are set
+export function experimentFromEnv(env) {
+  const dimensions = {};
+  Object.entries(EXPERIMENT_SETTINGS).forEach(([name, parameter]) => {
+    if (!env[parameter.setting]) return;
+    dimensions[name] = env[parameter.setting].split(',').map((v) => {
+      const value = parameter.values ? v.trim() : Number(v.trim());
+      checkValue(parameter, value, `${parameter.setting} values`);
+      return value;
+    });
+  });
+  if (env.EXPERIMENT_FILE) {
+    if (Object.keys(dimensions).length) {
+      throw new Error('Set EXPERIMENT_FILE or the EXPERIMENT_* lists, not both');
+    }
+    return loadExperimentFile(env.EXPERIMENT_FILE);
+  }
+  return Object.keys(dimensions).length ? createExperiment(dimensions) : undefined;
+}
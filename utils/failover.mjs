@@ -0,0 +1,72 @@
+// A prioritized pool of send endpoints with a simple circuit breaker. Sends
+// go to the highest priority endpoint whose circuit is closed. An endpoint's
+// circuit opens after `failureThreshold` consecutive send failures, or at
+// once on a 429, and closes again after `resetMs`.
+import { timestamp } from './misc.mjs';
+import { isRateLimited } from './ratelimit.mjs';
+
+// Preflight failures say something about the TX, not the endpoint
+function isEndpointFailure(error) {
+  return !error.message.includes('Transaction simulation failed');
+}
+
+// The endpoint failover is currently sending through, for the metrics
+export const activeSendEndpoint = { label: undefined, provider: undefined };
+
+const sendResult = ({ label, provider, region }) => ({ label, provider, region });
+
+export function createEndpointPool(sendConnections, { failureThreshold, resetMs }) {
+  const endpoints = sendConnections.map((sendConnection) => ({
+    ...sendConnection,
+    consecutiveFailures: 0,
+    openUntil: 0
+  }));
+  let activeLabel = endpoints[0].label;
+  let activeProvider = endpoints[0].provider;
+
+  const active = () => {
+    const now = Date.now();
+    const endpoint = endpoints.find((e) => e.openUntil <= now) ||
+      [...endpoints].sort((a, b) => a.openUntil - b.openUntil)[0];
+    if (endpoint.label !== activeLabel) {
+      console.log(`${timestamp()} Switching send endpoint from ${activeLabel} (${activeProvider}) to ${endpoint.label} (${endpoint.provider})`);
+      activeLabel = endpoint.label;
+      activeProvider = endpoint.provider;
+    }
+    activeSendEndpoint.label = activeLabel;
+    activeSendEndpoint.provider = activeProvider;
+    return endpoint;
+  };
+
+  const recordFailure = (endpoint, error) => {
+    if (!isEndpointFailure(error)) return;
+
+    endpoint.consecutiveFailures++;
+    if (isRateLimited(error) || endpoint.consecutiveFailures >= failureThreshold) {
+      console.log(`${timestamp()} ERROR: Opening circuit for ${endpoint.label} (${endpoint.provider}) after ${endpoint.consecutiveFailures} failures: ${error.message}`);
+      endpoint.openUntil = Date.now() + Number(resetMs);
+      endpoint.consecutiveFailures = 0;
+    }
+  };
+
+  return {
+    activeLabel: () => activeLabel,
+    activeProvider: () => activeProvider,
+
+    // Same return shape and errors as sendToAll, with one entry for the
+    // endpoint used.
+    async send(rawTransaction, options) {
+      const endpoint = active();
+      const start = Date.now();
+      try {
+        await endpoint.connection.sendRawTransaction(rawTransaction, options);
+      } catch (e) {
+        recordFailure(endpoint, e);
+        e.sendResults = [{ ...sendResult(endpoint), ms: Date.now() - start, error: e }];
+        throw e;
+      }
+      endpoint.consecutiveFailures = 0;
+      return [{ ...sendResult(endpoint), ms: Date.now() - start, error: undefined }];
+    }
+  };
+}
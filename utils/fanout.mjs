@@ -0,0 +1,47 @@
+// Send the same signed TX through several RPC endpoints at once and record
+// how each one did.
+import { rpcConnection } from './rpcheaders.mjs';
+
+// Endpoint URLs often carry an API key, so only log the host.
+export function endpointLabel(url) {
+  try {
+    return new URL(url).host;
+  } catch (e) {
+    return 'invalid-url';
+  }
+}
+
+// `config` is a commitment level or a web3.js ConnectionConfig. providers
+// names the RPC provider behind each endpoint, e.g. triton or self-hosted,
+// and defaults to the endpoint's host. regions gives each endpoint's region,
+// e.g. fra or nyc.
+export function createSendConnections(endpoints, config, { providers = [], regions = [] } = {}) {
+  return endpoints.map((url, i) => ({
+    label: endpointLabel(url),
+    provider: providers[i] || endpointLabel(url),
+    region: regions[i] || undefined,
+    connection: rpcConnection(url, config)
+  }));
+}
+
+// Returns one { label, provider, region, ms, error } entry per endpoint. Throws the first
+// error if no endpoint accepted the TX, with the entries attached as
+// error.sendResults.
+export async function sendToAll(sendConnections, rawTransaction, options) {
+  const results = await Promise.all(sendConnections.map(async ({ label, provider, region, connection }) => {
+    const start = Date.now();
+    try {
+      await connection.sendRawTransaction(rawTransaction, options);
+      return { label, provider, region, ms: Date.now() - start, error: undefined };
+    } catch (e) {
+      return { label, provider, region, ms: Date.now() - start, error: e };
+    }
+  }));
+
+  if (results.every((r) => r.error)) {
+    const error = results[0].error;
+    error.sendResults = results;
+    throw error;
+  }
+  return results;
+}
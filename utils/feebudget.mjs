@@ -0,0 +1,82 @@
+// Cap the fees spent per UTC day. Once the day's spend reaches the budget,
+// pinging pauses until midnight UTC, so a fee spike can't drain the wallet.
+// With a file the day's spend is kept on disk, so a restart doesn't reset it.
+import fs from 'fs';
+import { timestamp } from './misc.mjs';
+import { notify } from './notifier.mjs';
+
+// Shared state. spentLamports is the total for `day` (YYYY-MM-DD, UTC).
+export const feeBudget = {
+  maxDailyLamports: undefined,
+  day: undefined,
+  spentLamports: 0,
+  exhausted: false
+};
+
+let budgetFile;
+
+const today = () => new Date().toISOString().slice(0, 10);
+
+// file, if set, holds { day, spentLamports }. A spend from an earlier day is
+// ignored.
+export function configureFeeBudget(maxDailyLamports, file) {
+  feeBudget.maxDailyLamports = maxDailyLamports;
+  budgetFile = file;
+  if (!file || !fs.existsSync(file)) return;
+  try {
+    const { day, spentLamports } = JSON.parse(fs.readFileSync(file, 'utf8'));
+    if (day !== today() || !Number.isFinite(spentLamports)) return;
+    feeBudget.day = day;
+    feeBudget.spentLamports = spentLamports;
+    console.log(`${timestamp()} Loaded today's fee spend of ${spentLamports} lamports from ${file}`);
+    if (maxDailyLamports && spentLamports >= maxDailyLamports) {
+      feeBudget.exhausted = true;
+      console.log(`${timestamp()} WARNING: Today's fee budget is already spent. Pausing pings until 00:00 UTC.`);
+    }
+  } catch (e) {
+    console.log(`${timestamp()} ERROR: Unable to read the fee budget from ${file}: ${e.message}`);
+  }
+}
+
+function persist() {
+  if (!budgetFile) return;
+  try {
+    const tmp = `${budgetFile}.tmp`;
+    fs.writeFileSync(tmp, JSON.stringify({ day: feeBudget.day, spentLamports: feeBudget.spentLamports }));
+    fs.renameSync(tmp, budgetFile);
+  } catch (e) {
+    console.log(`${timestamp()} ERROR: Unable to write the fee budget to ${budgetFile}: ${e.message}`);
+  }
+}
+
+function rollOver() {
+  if (feeBudget.day === today()) return;
+  if (feeBudget.exhausted) {
+    console.log(`${timestamp()} New day, resuming pings after the fee budget pause`);
+  }
+  feeBudget.day = today();
+  feeBudget.spentLamports = 0;
+  feeBudget.exhausted = false;
+}
+
+export function recordFee(lamports) {
+  rollOver();
+  feeBudget.spentLamports += lamports;
+  if (lamports) persist();
+  if (
+    feeBudget.maxDailyLamports &&
+    !feeBudget.exhausted &&
+    feeBudget.spentLamports >= feeBudget.maxDailyLamports
+  ) {
+    feeBudget.exhausted = true;
+    const message = `Spent ${feeBudget.spentLamports} lamports in fees today, over MAX_DAILY_FEE_LAMPORTS (${feeBudget.maxDailyLamports}). Pausing pings until 00:00 UTC.`;
+    console.log(`${timestamp()} WARNING: ${message}`);
+    notify('fee_budget', message);
+  }
+}
+
+// True while pinging should pause
+export function feeBudgetExhausted() {
+  rollOver();
+  return feeBudget.exhausted;
+}
@@ -0,0 +1,304 @@
+// Priority fee providers. Each provider is { name, usesPercentile, fetch },
+// where fetch(percentile) resolves to a fee estimate { fee, strategy }: the
+// fee in micro-lamports per CU and how it was picked, e.g. rpc:p75, which is
+// reported with each ping. The watcher in priorityfees.mjs polls whichever
+// provider PRIORITY_FEE_SOURCE selects, so adding a source is a matter of
+// adding a function to PROVIDERS.
+//
+//   rpc     the percentile of getRecentPrioritizationFees for the ping's
+//           accounts, over the returned slots or a sliding window of the
+//           last PRIORITY_FEE_WINDOW_SLOTS slots seen
+//   fixed   a constant fee, PRIORITY_FEE_MICRO_LAMPORTS
+//   helius  getPriorityFeeEstimate at a priority level
+//   triton  getRecentPrioritizationFees with Triton's percentile extension,
+//           so the percentile is taken within each slot
+//   jito    the bundle tip floor at a percentile. We don't send bundles, so
+//           the tip is paid as a priority fee spread over the CU limit.
+//   target  the lowest fee that our own history in PING_SQLITE_DB says
+//           lands within PRIORITY_FEE_TARGET_SLOTS slots with probability
+//           PRIORITY_FEE_TARGET_PROBABILITY, see feetarget.mjs. Until there
+//           is enough history it falls back to the rpc source.
+import web3 from '@solana/web3.js';
+import { percentileOf } from './priorityfees.mjs';
+import { lowestLandingFee, fetchFeeHistory } from './feetarget.mjs';
+import { checkRateLimit } from './ratelimit.mjs';
+import { rpcHeaders } from './rpcheaders.mjs';
+
+export const HELIUS_PRIORITY_LEVELS = ['Min', 'Low', 'Medium', 'High', 'VeryHigh', 'UnsafeMax'];
+export const JITO_TIP_FLOOR_URL = 'https://bundles.jito.wtf/api/v1/bundles/tip_floor';
+// The percentiles the tip floor reports
+const JITO_TIP_PERCENTILES = [25, 50, 75, 95, 99];
+// How often the target source re-reads the ping history
+const TARGET_REFRESH_MS = 60000;
+
+async function rpcCall(url, method, params) {
+  const response = await fetch(url, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json', ...rpcHeaders(url) },
+    body: JSON.stringify({ jsonrpc: '2.0', id: 1, method: method, params: params }),
+    signal: AbortSignal.timeout(5000)
+  });
+  checkRateLimit(response, method);
+  if (!response.ok) {
+    throw new Error(`${method} returned ${response.status}`);
+  }
+  const body = await response.json();
+  if (body.error) throw new Error(body.error.message);
+  return body.result;
+}
+
+// getRecentPrioritizationFees isn't in our web3.js version, so call it
+// directly. With accountKeys (base58 addresses, at most 128) each slot's fee
+// is what it took to write lock those accounts, rather than the cluster-wide
+// minimum. Returns [{ slot, prioritizationFee }].
+export function fetchRecentPrioritizationFees(rpcEndpoint, accountKeys = []) {
+  return rpcCall(rpcEndpoint, 'getRecentPrioritizationFees', [accountKeys]);
+}
+
+// Helius's fee estimate for a TX touching accountKeys (base58 addresses) at
+// priorityLevel, one of HELIUS_PRIORITY_LEVELS.
+export async function fetchHeliusPriorityFeeEstimate(url, accountKeys, priorityLevel) {
+  const result = await rpcCall(url, 'getPriorityFeeEstimate', [
+    { accountKeys: accountKeys, options: { priorityLevel: priorityLevel } }
+  ]);
+  return Math.round(result.priorityFeeEstimate);
+}
+
+// Triton's getRecentPrioritizationFees takes the percentile (in basis
+// points) of the fees paid in each slot. Returns [{ slot, prioritizationFee }].
+export function fetchTritonPrioritizationFees(url, accountKeys, percentile) {
+  return rpcCall(url, 'getRecentPrioritizationFees', [
+    accountKeys,
+    { percentile: Math.round(percentile * 100) }
+  ]);
+}
+
+// Jito's landed bundle tip at the reported percentile nearest `percentile`,
+// in lamports
+export async function fetchJitoTipFloor(url, percentile) {
+  const response = await fetch(url, { signal: AbortSignal.timeout(5000) });
+  checkRateLimit(response, 'Jito tip floor');
+  if (!response.ok) throw new Error(`Jito tip floor returned ${response.status}`);
+  const [floor] = await response.json();
+  const nearest = JITO_TIP_PERCENTILES.reduce((best, p) =>
+    Math.abs(p - percentile) < Math.abs(best - percentile) ? p : best
+  );
+  return Math.round(floor[`landed_tips_${nearest}th_percentile`] * web3.LAMPORTS_PER_SOL);
+}
+
+// source => options => provider
+const PROVIDERS = {
+  rpc: ({ rpcEndpoint, accountKeys, windowSlots }) => {
+    // slot => fee for the sliding window
+    const window = new Map();
+    return {
+      usesPercentile: true,
+      fetch: async (percentile) => {
+        const fees = await fetchRecentPrioritizationFees(rpcEndpoint, accountKeys);
+        if (!windowSlots) {
+          return {
+            fee: percentileOf(fees.map((f) => f.prioritizationFee), percentile),
+            strategy: `rpc:p${percentile}`
+          };
+        }
+        fees.forEach((f) => window.set(f.slot, f.prioritizationFee));
+        const newest = Math.max(...window.keys());
+        [...window.keys()]
+          .filter((slot) => slot <= newest - windowSlots)
+          .forEach((slot) => window.delete(slot));
+        return {
+          fee: percentileOf([...window.values()], percentile),
+          strategy: `rpc:p${percentile}:window${windowSlots}`
+        };
+      }
+    };
+  },
+  fixed: ({ fixedFee }) => {
+    if (!(fixedFee >= 0)) {
+      throw new Error('PRIORITY_FEE_SOURCE=fixed needs PRIORITY_FEE_MICRO_LAMPORTS');
+    }
+    return {
+      usesPercentile: false,
+      fetch: async () => ({ fee: fixedFee, strategy: 'fixed' })
+    };
+  },
+  helius: ({ apiUrl, accountKeys, priorityLevel }) => {
+    if (!HELIUS_PRIORITY_LEVELS.includes(priorityLevel)) {
+      throw new Error(`PRIORITY_FEE_LEVEL must be one of ${HELIUS_PRIORITY_LEVELS.join(', ')}`);
+    }
+    return {
+      usesPercentile: false,
+      fetch: async () => ({
+        fee: await fetchHeliusPriorityFeeEstimate(apiUrl, accountKeys, priorityLevel),
+        strategy: `helius:${priorityLevel}`
+      })
+    };
+  },
+  triton: ({ apiUrl, accountKeys }) => ({
+    usesPercentile: true,
+    fetch: async (percentile) => {
+      const fees = await fetchTritonPrioritizationFees(apiUrl, accountKeys, percentile);
+      return {
+        fee: percentileOf(fees.map((f) => f.prioritizationFee), 50),
+        strategy: `triton:p${percentile}`
+      };
+    }
+  }),
+  jito: ({ apiUrl, computeUnitLimit }) => ({
+    usesPercentile: true,
+    fetch: async (percentile) => {
+      const tipLamports = await fetchJitoTipFloor(apiUrl, percentile);
+      return {
+        fee: Math.ceil(tipLamports * 1000000 / Number(computeUnitLimit)),
+        strategy: `jito:p${percentile}`
+      };
+    }
+  }),
+  target: (options) => {
+    const { sqliteDb, target } = options;
+    if (!sqliteDb) throw new Error('PRIORITY_FEE_SOURCE=target needs PING_SQLITE_DB');
+    if (!(target.probability > 0 && target.probability <= 1)) {
+      throw new Error('PRIORITY_FEE_TARGET_PROBABILITY must be between 0 and 1');
+    }
+    const fallback = PROVIDERS.rpc(options);
+    let choice;
+    let refreshedAt = 0;
+    return {
+      usesPercentile: true,
+      fetch: async (percentile) => {
+        if (Date.now() - refreshedAt > TARGET_REFRESH_MS) {
+          const rows = await fetchFeeHistory(sqliteDb, Date.now() - target.windowMs);
+          choice = lowestLandingFee(rows, target);
+          refreshedAt = Date.now();
+        }
+        if (!choice) {
+          const estimate = await fallback.fetch(percentile);
+          return { ...estimate, strategy: `target:fallback:${estimate.strategy}` };
+        }
+        return {
+          fee: choice.fee,
+          strategy: `target:p${target.probability}:k${target.slots}`
+        };
+      }
+    };
+  }
+};
+
+export const PRIORITY_FEE_SOURCES = Object.keys(PROVIDERS);
+
+// The provider for `source`. options holds what the providers need:
+// rpcEndpoint, apiUrl, accountKeys, priorityLevel, computeUnitLimit,
+// fixedFee, windowSlots, sqliteDb, target and percentile. Throws if the source is unknown or its settings
+// are missing.
+export function createPriorityFeeProvider(source, options) {
+  if (!PROVIDERS[source]) {
+    throw new Error(`PRIORITY_FEE_SOURCE must be one of ${PRIORITY_FEE_SOURCES.join(', ')}`);
+  }
+  const provider = { name: source, ...PROVIDERS[source](options) };
+  if (provider.usesPercentile && !options.percentile) {
+    throw new Error(`PRIORITY_FEE_SOURCE=${source} needs PRIORITY_FEE_PERCENTILE`);
+  }
+  return provider;
+}
+
+// Smooth a provider's fees with an exponential moving average. Before it is
+// averaged in, each raw fee is clamped to spikeFactor times the current
+// average, so a single anomalous slot can't multiply the fee for a cycle.
+// alpha (0 < alpha <= 1) is the weight of the newest fee.
+export function withSmoothing(provider, alpha, spikeFactor) {
+  let average;
+  return {
+    ...provider,
+    fetch: async (percentile) => {
+      const estimate = await provider.fetch(percentile);
+      if (estimate.fee === undefined) return estimate;
+      if (average === undefined) {
+        average = estimate.fee;
+      } else {
+        const clamped = spikeFactor && average > 0
+          ? Math.min(estimate.fee, average * spikeFactor)
+          : estimate.fee;
+        average = alpha * clamped + (1 - alpha) * average;
+      }
+      return {
+        fee: Math.round(average),
+        rawFee: estimate.fee,
+        strategy: `${estimate.strategy}:ema${alpha}`
+      };
+    }
+  };
+}
+
+// Bound a provider's fees to [min, max], either of which may be undefined.
+// Clamped estimates say which bound applied in `clamped`.
+export function withClamps(provider, min, max) {
+  return {
+    ...provider,
+    fetch: async (percentile) => {
+      const estimate = await provider.fetch(percentile);
+      const rawFee = estimate.rawFee === undefined ? estimate.fee : estimate.rawFee;
+      if (estimate.fee === undefined) return estimate;
+      if (min !== undefined && estimate.fee < min) {
+        return { ...estimate, fee: min, rawFee: rawFee, clamped: 'min' };
+      }
+      if (max !== undefined && estimate.fee > max) {
+        return { ...estimate, fee: max, rawFee: rawFee, clamped: 'max' };
+      }
+      return estimate;
+    }
+  };
+}
+
+const optionalNumber = (value) => value === undefined || value === '' ? undefined : Number(value);
+
+// The provider the PRIORITY_FEE_* settings in `env` select, or undefined
+// when pings go without a priority fee (the rpc source without a
+// percentile). accountKeys are the accounts a ping touches, to which
+// PRIORITY_FEE_ACCOUNTS adds any others whose fees should count.
+export function priorityFeeProviderFromEnv(env, accountKeys) {
+  const source = env.PRIORITY_FEE_SOURCE || 'rpc';
+  if (source === 'rpc' && !env.PRIORITY_FEE_PERCENTILE) return undefined;
+  const extraAccounts = (env.PRIORITY_FEE_ACCOUNTS || '').split(',').map((a) => a.trim()).filter((a) => a);
+  extraAccounts.forEach((account) => {
+    try {
+      new web3.PublicKey(account);
+    } catch (e) {
+      throw new Error(`PRIORITY_FEE_ACCOUNTS: ${account} is not a valid address`);
+    }
+  });
+  const provider = createPriorityFeeProvider(source, {
+    rpcEndpoint: env.RPC_ENDPOINT,
+    apiUrl: env.PRIORITY_FEE_API_URL || (source === 'jito' ? JITO_TIP_FLOOR_URL : env.RPC_ENDPOINT),
+    accountKeys: [...new Set([...accountKeys, ...extraAccounts])],
+    priorityLevel: env.PRIORITY_FEE_LEVEL || 'Medium',
+    computeUnitLimit: Number(env.COMPUTE_UNIT_LIMIT || 1000),
+    windowSlots: Number(env.PRIORITY_FEE_WINDOW_SLOTS || 0),
+    sqliteDb: env.PING_SQLITE_DB,
+    target: {
+      probability: Number(env.PRIORITY_FEE_TARGET_PROBABILITY || 0.9),
+      slots: Number(env.PRIORITY_FEE_TARGET_SLOTS || 2),
+      windowMs: Number(env.PRIORITY_FEE_TARGET_WINDOW_MS || 21600000),
+      minSamples: Number(env.PRIORITY_FEE_TARGET_MIN_SAMPLES || 20)
+    },
+    fixedFee: optionalNumber(env.PRIORITY_FEE_MICRO_LAMPORTS),
+    percentile: env.PRIORITY_FEE_PERCENTILE
+  });
+
+  let smoothed = provider;
+  if (env.PRIORITY_FEE_EMA_ALPHA) {
+    const alpha = Number(env.PRIORITY_FEE_EMA_ALPHA);
+    if (!(alpha > 0 && alpha <= 1)) {
+      throw new Error('PRIORITY_FEE_EMA_ALPHA must be between 0 and 1');
+    }
+    smoothed = withSmoothing(provider, alpha, Number(env.PRIORITY_FEE_SPIKE_FACTOR || 3));
+  }
+
+  // PRIORITY_FEE_MIN and PRIORITY_FEE_MAX bound whatever the strategy picks
+  const min = optionalNumber(env.PRIORITY_FEE_MIN);
+  const max = optionalNumber(env.PRIORITY_FEE_MAX);
+  if (min === undefined && max === undefined) return smoothed;
+  if (min !== undefined && max !== undefined && min > max) {
+    throw new Error('PRIORITY_FEE_MIN must not be above PRIORITY_FEE_MAX');
+  }
+  return withClamps(smoothed, min, max);
+}
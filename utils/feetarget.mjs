@@ -0,0 +1,50 @@
+// Pick the lowest priority fee that our own history says lands within
+// `slots` slots with at least `probability`. Past pings are grouped into
+// power-of-two fee buckets; the answer is the highest fee seen in the
+// cheapest bucket that has minSamples pings and lands often enough.
+import { querySqlite } from './sqlite.mjs';
+
+// Power-of-two fee buckets: 0, 1, 2-3, 4-7, ...
+export const feeBucket = (fee) => Math.floor(Math.log2(fee + 1));
+
+export const feeBucketLabel = (bucket) => {
+  const low = 2 ** bucket - 1;
+  const high = 2 ** (bucket + 1) - 2;
+  return low === high ? String(low) : `${low}-${high}`;
+};
+
+// rows are { fee, success, slot_sent, slot_landed }. Returns
+// { fee, landingRate, samples } or undefined if no bucket qualifies.
+export function lowestLandingFee(rows, { slots, probability, minSamples }) {
+  const buckets = new Map();
+  rows.forEach((row) => {
+    const bucket = feeBucket(row.fee);
+    const stats = buckets.get(bucket) || { samples: 0, landed: 0, maxFee: 0 };
+    stats.samples++;
+    if (row.success && row.slot_landed !== null && row.slot_landed - row.slot_sent <= slots) {
+      stats.landed++;
+    }
+    stats.maxFee = Math.max(stats.maxFee, row.fee);
+    buckets.set(bucket, stats);
+  });
+
+  const match = [...buckets.entries()]
+    .sort(([a], [b]) => a - b)
+    .map(([, stats]) => stats)
+    .find((stats) => stats.samples >= minSamples && stats.landed / stats.samples >= probability);
+  return match && {
+    fee: match.maxFee,
+    landingRate: match.landed / match.samples,
+    samples: match.samples
+  };
+}
+
+// Pings sent since sinceMs from the SQLite store, with the fields
+// lowestLandingFee needs. Pings without a priority fee count as fee 0.
+export function fetchFeeHistory(db, sinceMs) {
+  const since = new Date(sinceMs).toISOString();
+  return querySqlite(db, `
+    SELECT COALESCE(priority_fee_micro_lamports, 0) AS fee, success, slot_sent, slot_landed
+    FROM pings
+    WHERE sent_at >= '${since}' AND slot_sent IS NOT NULL`);
+}
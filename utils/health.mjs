@@ -0,0 +1,53 @@
+// /healthz and /readyz. healthz always answers with the freshness of each
+// watcher so uptime monitors can see what's going on. readyz returns 503
+// until the slot and block height feeds are fresh.
+import { addRoute, jsonResponse } from './server.mjs';
+import { globalSlot } from './slot.mjs';
+import { globalBlockHeight } from './blockheight.mjs';
+import { watcherRestarts } from './supervisor.mjs';
+
+// Updated by the ping loop. blockhashAgeMs is how old the latest ping's
+// blockhash was when the TX was sent.
+export const pingState = {
+  lastPingAt: undefined,
+  lastPingSuccess: undefined,
+  blockhashAgeMs: undefined
+};
+
+const ageMs = (updatedAt) => updatedAt === undefined ? null : Date.now() - updatedAt;
+
+export function healthStatus({ slotMaxAgeMs, blockHeightMaxAgeMs }) {
+  const slotAgeMs = ageMs(globalSlot.updatedAt);
+  const blockHeightAgeMs = ageMs(globalBlockHeight.updatedAt);
+  const slotFresh = slotAgeMs !== null && slotAgeMs <= slotMaxAgeMs;
+  const blockHeightFresh = blockHeightAgeMs !== null &&
+    blockHeightAgeMs <= blockHeightMaxAgeMs;
+
+  return {
+    ready: slotFresh && blockHeightFresh,
+    watchers: {
+      slot: {
+        fresh: slotFresh,
+        age_ms: slotAgeMs,
+        slot: globalSlot.slot,
+        stale_events: globalSlot.staleEvents
+      },
+      block_height: {
+        fresh: blockHeightFresh,
+        age_ms: blockHeightAgeMs,
+        block_height: globalBlockHeight.blockHeight
+      }
+    },
+    watcher_restarts: watcherRestarts,
+    last_ping_age_ms: ageMs(pingState.lastPingAt),
+    last_ping_success: pingState.lastPingSuccess
+  };
+}
+
+export function addHealthRoutes(freshness) {
+  addRoute('/healthz', () => jsonResponse(200, healthStatus(freshness)));
+  addRoute('/readyz', () => {
+    const status = healthStatus(freshness);
+    return jsonResponse(status.ready ? 200 : 503, status);
+  });
+}
@@ -0,0 +1,66 @@
+// Write each ping result to InfluxDB as a line protocol point.
+// writeUrl is the full write endpoint, e.g.
+// http://localhost:8086/api/v2/write?org=myorg&bucket=pings&precision=ms
+import { timestamp } from './misc.mjs';
+
+const MEASUREMENT = 'ping_thing';
+
+const escapeTag = (value) => String(value).replace(/([,= \\])/g, '\\$1');
+
+function fieldValue(value) {
+  if (typeof value === 'boolean') return String(value);
+  if (Number.isInteger(value)) return `${value}i`;
+  if (typeof value === 'number') return String(value);
+  return `"${String(value).replace(/(["\\])/g, '\\$1')}"`;
+}
+
+function toLine(tags, fields, timeMs) {
+  const tagString = Object.entries(tags)
+    .filter(([, v]) => v !== undefined && v !== '')
+    .map(([k, v]) => `,${k}=${escapeTag(v)}`)
+    .join('');
+  const fieldString = Object.entries(fields)
+    .filter(([, v]) => v !== undefined && v !== null)
+    .map(([k, v]) => `${k}=${fieldValue(v)}`)
+    .join(',');
+  return `${MEASUREMENT}${tagString} ${fieldString} ${timeMs}`;
+}
+
+// `result` is the ping payload sent to validators.app.
+export function pingResultLine(result, { pingerName, region, timeMs }) {
+  return toLine(
+    {
+      pinger: pingerName,
+      region: region,
+      commitment: result.commitment_level,
+      reason: result.reason
+    },
+    {
+      time_ms: result.time,
+      success: result.success,
+      slot_latency: result.slot_landed === undefined || result.slot_sent === undefined
+        ? undefined
+        : result.slot_landed - result.slot_sent,
+      fee: result.priority_fee_micro_lamports,
+      compute_units: result.compute_units_consumed,
+      fee_lamports: result.fee_lamports,
+      tx_index: result.tx_index,
+      resends: result.resends,
+      signature: result.signature
+    },
+    timeMs
+  );
+}
+
+export async function writeToInflux(writeUrl, token, line) {
+  try {
+    const headers = { 'Content-Type': 'text/plain; charset=utf-8' };
+    if (token) headers.Authorization = `Token ${token}`;
+    const response = await fetch(writeUrl, { method: 'POST', headers, body: line });
+    if (!response.ok) {
+      console.log(`${timestamp()} ERROR: InfluxDB write returned ${response.status}: ${await response.text()}`);
+    }
+  } catch (e) {
+    console.log(`${timestamp()} ERROR: InfluxDB write failed: ${e.message}`);
+  }
+}
@@ -0,0 +1,53 @@
+// Publish ping events to a Kafka topic through a Kafka REST Proxy (v2 API),
+// which avoids a native Kafka client dependency. Events are keyed by
+// signature and sent in batches every intervalMs. Failed batches are kept
+// and retried, up to maxBuffered events, unless the proxy rejected them
+// outright.
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+import { isRetryableStatus } from './vaqueue.mjs';
+
+const PRODUCE_TIMEOUT_MS = 10000;
+
+let records = [];
+
+export const kafkaBufferSize = () => records.length;
+
+export function kafkaSink(maxBuffered) {
+  return (event) => {
+    records.push({ key: event.signature, value: event });
+    if (records.length > maxBuffered) records.shift();
+  };
+}
+
+export async function runKafkaProducer(restProxyUrl, topic, intervalMs) {
+  const url = `${restProxyUrl.replace(/\/$/, '')}/topics/${encodeURIComponent(topic)}`;
+  let failures = 0;
+  while (true) {
+    await sleep(failures ? backoffMs(failures, intervalMs) : intervalMs);
+    if (records.length === 0) continue;
+
+    const batch = records;
+    records = [];
+    try {
+      const response = await fetch(url, {
+        method: 'POST',
+        headers: {
+          'Content-Type': 'application/vnd.kafka.json.v2+json',
+          'Accept': 'application/vnd.kafka.v2+json'
+        },
+        body: JSON.stringify({ records: batch }),
+        signal: AbortSignal.timeout(PRODUCE_TIMEOUT_MS)
+      });
+      if (isRetryableStatus(response.status)) throw new Error(`HTTP ${response.status}`);
+      // e.g. an unknown topic, which a retry won't fix
+      if (!response.ok) {
+        console.log(`${timestamp()} ERROR: Kafka topic ${topic} rejected ${batch.length} events with ${response.status}, dropping them`);
+      }
+      failures = 0;
+    } catch (e) {
+      failures++;
+      records = batch.concat(records);
+      console.log(`${timestamp()} ERROR: Unable to publish ${batch.length} events to Kafka topic ${topic} (${failures} in a row): ${e.message}`);
+    }
+  }
+}
@@ -0,0 +1,67 @@
+// Alarm on the landing rate, the share of the last `window` pings that
+// confirmed within their deadline. Falling below the threshold is logged
+// as an error and posted to an optional webhook and the chat notifier, and
+// so is recovering.
+import { timestamp } from './misc.mjs';
+import { notify as notifyChat } from './notifier.mjs';
+
+const WEBHOOK_TIMEOUT_MS = 5000;
+
+let results = [];
+let alarmConfig;
+
+export const landingAlarm = {
+  rate: undefined,
+  firing: false
+};
+
+// threshold is a fraction, e.g. 0.8. The alarm only evaluates once `window`
+// pings have been seen.
+export function configureLandingAlarm({ window, threshold, webhookUrl, pingerName }) {
+  alarmConfig = { window, threshold, webhookUrl, pingerName };
+}
+
+function notify(event, message) {
+  notifyChat('landing_rate', message);
+  if (!alarmConfig.webhookUrl) return;
+  fetch(alarmConfig.webhookUrl, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({
+      event: event,
+      pinger_name: alarmConfig.pingerName,
+      landing_rate: landingAlarm.rate,
+      threshold: alarmConfig.threshold,
+      window: alarmConfig.window,
+      message: message,
+      time: timestamp()
+    }),
+    signal: AbortSignal.timeout(WEBHOOK_TIMEOUT_MS)
+  }).catch((e) => {
+    console.log(`${timestamp()} ERROR: Unable to post landing rate alert: ${e.message}`);
+  });
+}
+
+export function recordLanding(success) {
+  if (!alarmConfig) return;
+
+  results.push(success);
+  if (results.length > alarmConfig.window) results = results.slice(-alarmConfig.window);
+  if (results.length < alarmConfig.window) return;
+
+  landingAlarm.rate = results.filter((r) => r).length / results.length;
+  const percent = `${(landingAlarm.rate * 100).toFixed(1)}%`;
+  const threshold = `${(alarmConfig.threshold * 100).toFixed(1)}%`;
+
+  if (!landingAlarm.firing && landingAlarm.rate < alarmConfig.threshold) {
+    landingAlarm.firing = true;
+    const message = `Landing rate ${percent} over the last ${alarmConfig.window} pings is below ${threshold}`;
+    console.log(`${timestamp()} ERROR: ${message}`);
+    notify('landing_rate_low', message);
+  } else if (landingAlarm.firing && landingAlarm.rate >= alarmConfig.threshold) {
+    landingAlarm.firing = false;
+    const message = `Landing rate recovered to ${percent} over the last ${alarmConfig.window} pings`;
+    console.log(`${timestamp()} ${message}`);
+    notify('landing_rate_recovered', message);
+  }
+}
@@ -0,0 +1,62 @@
+// Keep an eye on TXs that timed out. If one lands during the grace period we
+// log it as a late landing so "slow" can be told apart from "dropped", and
+// tell onOutcome so it can be counted and stored with the ping.
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+import { rateLimitedDelay, noteRateLimit } from './ratelimit.mjs';
+import { commitmentReached } from './confirm.mjs';
+
+// getSignatureStatuses accepts at most 256 signatures per call
+const MAX_SIGNATURES_PER_CALL = 256;
+
+// signature => { sentAt, expiresAt } (millisecond timestamps)
+const timedOutSignatures = new Map();
+
+export function trackTimedOutSignature(signature, sentAt, graceMs) {
+  timedOutSignatures.set(signature, {
+    sentAt: sentAt,
+    expiresAt: Date.now() + Number(graceMs)
+  });
+}
+
+// onOutcome(signature, { outcome, slot, ms }) is called once per timed out TX
+// with outcome landed (in slot, ms after send) or dropped.
+export async function watchLateLandings(connection, commitment, intervalMs, onOutcome) {
+  let failures = 0;
+  while (true) {
+    await sleep(rateLimitedDelay(failures ? backoffMs(failures, intervalMs) : intervalMs));
+    if (timedOutSignatures.size === 0) continue;
+
+    const signatures = [...timedOutSignatures.keys()];
+    for (let i = 0; i < signatures.length; i += MAX_SIGNATURES_PER_CALL) {
+      const batch = signatures.slice(i, i + MAX_SIGNATURES_PER_CALL);
+      try {
+        const { value } = await connection.getSignatureStatuses(
+          batch,
+          { searchTransactionHistory: true }
+        );
+        value.forEach((status, index) => {
+          const signature = batch[index];
+          const { sentAt, expiresAt } = timedOutSignatures.get(signature);
+          if (commitmentReached(status, commitment)) {
+            const ms = Date.now() - sentAt;
+            console.log(`${timestamp()} LATE LANDING: ${signature} landed in slot ${status.slot} ${ms}ms after send`);
+            timedOutSignatures.delete(signature);
+            onOutcome(signature, { outcome: 'landed', slot: status.slot, ms: ms });
+          } else if (Date.now() > expiresAt) {
+            console.log(`${timestamp()} DROPPED: ${signature} never landed`);
+            timedOutSignatures.delete(signature);
+            onOutcome(signature, { outcome: 'dropped' });
+          }
+        });
+        failures = 0;
+      } catch (e) {
+        failures++;
+        noteRateLimit('late_landing', e);
+        console.log(`${timestamp()} ERROR: Late landing check failed (${failures} in a row): ${e.message}`);
+      }
+    }
+  }
+}
+
+// Number of timed out TXs still being watched.
+export const lateLandingQueueSize = () => timedOutSignatures.size;
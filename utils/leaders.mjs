@@ -0,0 +1,63 @@
+// Cache the leader schedule for the current epoch so each ping can record
+// which validator was leader when it was sent and when it landed.
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+import { rateLimitedDelay, noteRateLimit } from './ratelimit.mjs';
+
+// leaders[i] is the identity of the leader for slot firstSlot + i
+const leaderSchedule = {
+  epoch: undefined,
+  firstSlot: undefined,
+  leaders: []
+};
+
+async function refreshLeaderSchedule(connection) {
+  const epochInfo = await connection.getEpochInfo();
+  if (epochInfo.epoch === leaderSchedule.epoch) return;
+
+  const schedule = await connection.getLeaderSchedule();
+  const leaders = new Array(epochInfo.slotsInEpoch);
+  Object.entries(schedule).forEach(([identity, slotIndexes]) => {
+    slotIndexes.forEach((slotIndex) => { leaders[slotIndex] = identity; });
+  });
+
+  leaderSchedule.epoch = epochInfo.epoch;
+  leaderSchedule.firstSlot = epochInfo.absoluteSlot - epochInfo.slotIndex;
+  leaderSchedule.leaders = leaders;
+  console.log(`${timestamp()} Loaded leader schedule for epoch ${epochInfo.epoch}`);
+}
+
+// Check for a new epoch every intervalMs and reload the schedule when it
+// changes. Retries back off after errors.
+export async function watchLeaderSchedule(connection, intervalMs) {
+  let failures = 0;
+  while (true) {
+    try {
+      await refreshLeaderSchedule(connection);
+      failures = 0;
+    } catch (e) {
+      failures++;
+      noteRateLimit('leader_schedule', e);
+      console.log(`${timestamp()} ERROR: Unable to load leader schedule (${failures} in a row): ${e.message}`);
+    }
+    // Retry a failed load sooner than the normal poll, but back off
+    await sleep(rateLimitedDelay(failures ? Math.min(intervalMs, backoffMs(failures, 1000)) : intervalMs));
+  }
+}
+
+// The leader identity for a slot, or undefined if it isn't in the cached
+// epoch.
+export function leaderForSlot(slot) {
+  if (slot === undefined || leaderSchedule.firstSlot === undefined) return undefined;
+  return leaderSchedule.leaders[slot - leaderSchedule.firstSlot];
+}
+
+// How many slots the current leader has left after `slot` before the next
+// leader takes over. 0 means `slot` is the last slot of the rotation.
+export function slotsUntilLeaderRotation(slot) {
+  const leader = leaderForSlot(slot);
+  if (leader === undefined) return undefined;
+
+  let remaining = 0;
+  while (leaderForSlot(slot + remaining + 1) === leader) remaining++;
+  return remaining;
+}
@@ -0,0 +1,70 @@
+// Rolling per-leader statistics, keyed by the leader at the time a ping was
+// sent. Used to spot validators that consistently delay or drop pings.
+import { sleep, timestamp } from './misc.mjs';
+
+// leader identity => [{ landed, slotLatency }], newest last
+const leaderResults = new Map();
+
+export function median(values) {
+  if (values.length === 0) return undefined;
+  const sorted = [...values].sort((a, b) => a - b);
+  const middle = Math.floor(sorted.length / 2);
+  return sorted.length % 2 === 0
+    ? (sorted[middle - 1] + sorted[middle]) / 2
+    : sorted[middle];
+}
+
+export function recordLeaderResult(leader, landed, slotLatency, windowSize) {
+  if (leader === undefined) return;
+
+  const results = leaderResults.get(leader) || [];
+  results.push({ landed, slotLatency });
+  if (results.length > windowSize) results.shift();
+  leaderResults.set(leader, results);
+}
+
+export function leaderStats() {
+  return [...leaderResults.entries()].map(([leader, results]) => {
+    const landed = results.filter((r) => r.landed);
+    return {
+      leader: leader,
+      pings: results.length,
+      landingRate: landed.length / results.length,
+      medianSlotLatency: median(
+        landed.map((r) => r.slotLatency).filter((l) => l !== undefined)
+      )
+    };
+  });
+}
+
+// Log the leaders with the worst landing rate every intervalMs.
+export async function logLeaderStats(intervalMs, count) {
+  while (true) {
+    await sleep(intervalMs);
+
+    const stats = leaderStats()
+      .sort((a, b) => a.landingRate - b.landingRate)
+      .slice(0, count);
+    console.log(`${timestamp()} Leader stats (worst ${stats.length} by landing rate):`);
+    stats.forEach((s) => {
+      console.log(`  ${s.leader} pings: ${s.pings} landing rate: ${(s.landingRate * 100).toFixed(1)}% median slot latency: ${s.medianSlotLatency}`);
+    });
+  }
+}
+
+// Number of leaders we hold stats for.
+export const trackedLeaderCount = () => leaderResults.size;
+
+// Only the leaders we pinged most are exported as metrics, to keep the
+// number of leader label values down
+let metricsLeaderCount = 20;
+
+export function configureLeaderMetrics(count) {
+  metricsLeaderCount = count;
+}
+
+// Stats for the metricsLeaderCount leaders with the most pings in the window
+export const topLeaderStats = () =>
+  leaderStats()
+    .sort((a, b) => b.pings - a.pings || a.leader.localeCompare(b.leader))
+    .slice(0, metricsLeaderCount);
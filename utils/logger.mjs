@@ -0,0 +1,111 @@
+// Log output options. Log lines across the client follow the
+// "<ISO timestamp> [LEVEL: ]message" convention and are printed with
+// console.log, so this wraps console.log to:
+// - write JSON for Loki/ELK: { time, level, msg, ...context }. The context
+//   holds the fields of the ping in flight (signature, slot_sent, fee) so
+//   every line can be queried per transaction. It is kept in
+//   AsyncLocalStorage, so only code running on behalf of the ping gets it and
+//   watchers started earlier log without it.
+// - also append to a log file with size and/or daily rotation.
+import fs from 'fs';
+import util from 'util';
+import { AsyncLocalStorage } from 'async_hooks';
+
+const LINE_PATTERN = /^(\d{4}-\d\d-\d\dT[\d:.]+Z) (?:(ERROR|WARNING|FATAL|DROPPED|LATE LANDING): )?([\s\S]*)$/;
+const LEVELS = {
+  ERROR: 'error',
+  WARNING: 'warn',
+  FATAL: 'fatal',
+  DROPPED: 'warn',
+  'LATE LANDING': 'warn'
+};
+
+let jsonOutput = false;
+const logContext = new AsyncLocalStorage();
+let logFile;
+const writeStdout = console.log.bind(console);
+
+// Tag the lines logged from here on in the current async flow, and from
+// anything it starts, with fields
+export function setLogContext(fields) {
+  logContext.enterWith(fields);
+}
+
+// Appends lines to `path`. Before a write would take the file past
+// maxSizeBytes, or on the first write of a new UTC day when rotateDaily is
+// set, the file is rotated: path => path.1 => path.2 ... and anything past
+// path.<maxFiles> is deleted.
+function openLogFile(path, { maxSizeBytes, maxFiles, rotateDaily }) {
+  const today = () => new Date().toISOString().slice(0, 10);
+  let fd = fs.openSync(path, 'a');
+  let size = fs.fstatSync(fd).size;
+  let day = today();
+
+  const rotate = () => {
+    fs.closeSync(fd);
+    for (let i = maxFiles - 1; i >= 1; i--) {
+      if (fs.existsSync(`${path}.${i}`)) fs.renameSync(`${path}.${i}`, `${path}.${i + 1}`);
+    }
+    if (maxFiles > 0) {
+      fs.renameSync(path, `${path}.1`);
+    } else {
+      fs.unlinkSync(path);
+    }
+    fs.rmSync(`${path}.${maxFiles + 1}`, { force: true });
+    fd = fs.openSync(path, 'a');
+    size = 0;
+  };
+
+  return (line) => {
+    const data = line + '\n';
+    const bytes = Buffer.byteLength(data);
+    const newDay = rotateDaily && today() !== day;
+    if (size > 0 && (newDay || (maxSizeBytes && size + bytes > maxSizeBytes))) rotate();
+    day = today();
+    fs.writeSync(fd, data);
+    size += bytes;
+  };
+}
+
+function writeLine(line) {
+  writeStdout(line);
+  if (logFile) {
+    try {
+      logFile(line);
+    } catch (e) {
+      writeStdout(`${new Date().toISOString()} ERROR: Unable to write log file: ${e.message}`);
+    }
+  }
+}
+
+function formatLine(args) {
+  const text = util.format(...args);
+  if (!jsonOutput) return text;
+
+  const trimmed = text.trim();
+  const match = trimmed.match(LINE_PATTERN);
+  return JSON.stringify({
+    time: match ? match[1] : new Date().toISOString(),
+    level: match && match[2] ? LEVELS[match[2]] : 'info',
+    msg: match ? match[3] : trimmed,
+    ...logContext.getStore()
+  });
+}
+
+// format is text or json. file (optional) is a path to also log to.
+export function configureLogging({ format, file, maxSizeBytes, maxFiles, rotateDaily }) {
+  jsonOutput = format === 'json';
+  if (file) logFile = openLogFile(file, { maxSizeBytes, maxFiles, rotateDaily });
+  if (jsonOutput || logFile) {
+    console.log = (...args) => writeLine(formatLine(args));
+  }
+}
+
+// A structured event. In text mode this is a no-op because the plain log
+// lines already carry the same information. time, level and msg can't be
+// overridden by fields.
+export function logEvent(msg, fields) {
+  if (!jsonOutput) return;
+  const line = { time: new Date().toISOString(), level: 'info', msg: msg };
+  writeLine(JSON.stringify(Object.assign({ ...line }, logContext.getStore(), fields, line)));
+}
@@ -0,0 +1,18 @@
+// Watch our own memory use. If RSS goes over the configured cap we exit so
+// the service manager restarts us cleanly instead of the OOM killer doing
+// it at a random point.
+import { sleep, timestamp } from './misc.mjs';
+import { sdNotifySync } from './systemd.mjs';
+
+export async function watchMemory(maxRssMb, intervalMs) {
+  while (true) {
+    await sleep(intervalMs);
+
+    const rssMb = process.memoryUsage().rss / 1024 / 1024;
+    if (rssMb > maxRssMb) {
+      console.log(`${timestamp()} ERROR: RSS ${rssMb.toFixed(0)}MB is over MAX_RSS_MB (${maxRssMb}MB). Exiting so we can be restarted`);
+      sdNotifySync('STOPPING=1');
+      process.exit(1);
+    }
+  }
+}
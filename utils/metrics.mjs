@@ -0,0 +1,130 @@
+// A minimal Prometheus registry rendered in the text exposition format.
+// Counters, gauges and histograms take a labels object on every update.
+// Counters and gauges can instead be given a collect function that is called
+// at scrape time. Default labels (e.g. pinger_name) are added to every series
+// and must be set before any metric is updated.
+const metrics = [];
+let defaultLabels = {};
+// Other backends (e.g. StatsD) that want every update as it happens
+const sinks = [];
+
+export function setDefaultLabels(labels) {
+  defaultLabels = labels;
+}
+
+// sink.emit(type, name, labels, value) is called for every counter
+// increment, gauge set and histogram observation.
+export function addSink(sink) {
+  sinks.push(sink);
+}
+
+const emit = (type, name, labels, value) => {
+  sinks.forEach((sink) => sink.emit(type, name, { ...defaultLabels, ...labels }, value));
+};
+
+// Runs the collect functions and returns
+// [{ type, name, series: [{ labels, value }] }] for sinks that poll.
+export function collectMetrics() {
+  return metrics
+    .filter((metric) => metric.collect)
+    .map((metric) => ({
+      type: metric.type,
+      name: metric.name,
+      series: metric.collect()
+        .filter(({ value }) => value !== undefined && value !== null)
+        .map(({ labels, value }) => ({ labels: { ...defaultLabels, ...labels }, value }))
+    }));
+}
+
+const escapeLabel = (value) =>
+  String(value).replace(/\\/g, '\\\\').replace(/"/g, '\\"').replace(/\n/g, '\\n');
+
+function labelString(labels) {
+  const entries = Object.entries({ ...defaultLabels, ...labels })
+    .filter(([, v]) => v !== undefined);
+  if (entries.length === 0) return '';
+  return `{${entries.map(([k, v]) => `${k}="${escapeLabel(v)}"`).join(',')}}`;
+}
+
+// Series are stored by their rendered label string
+function register(name, help, type, collect) {
+  const metric = { name, help, type, collect, series: new Map() };
+  metrics.push(metric);
+  return metric;
+}
+
+export function counter(name, help, collect) {
+  const metric = register(name, help, 'counter', collect);
+  return {
+    inc(labels = {}, value = 1) {
+      const key = labelString(labels);
+      metric.series.set(key, (metric.series.get(key) || 0) + value);
+      emit('counter', name, labels, value);
+    }
+  };
+}
+
+export function gauge(name, help, collect) {
+  const metric = register(name, help, 'gauge', collect);
+  return {
+    set(labels, value) {
+      metric.series.set(labelString(labels), value);
+      emit('gauge', name, labels, value);
+    }
+  };
+}
+
+export const DEFAULT_LATENCY_BUCKETS_MS = [
+  250, 500, 1000, 2000, 3000, 5000, 7500, 10000, 15000, 20000, 30000, 60000
+];
+
+export function histogram(name, help, buckets = DEFAULT_LATENCY_BUCKETS_MS) {
+  const metric = register(name, help, 'histogram');
+  metric.buckets = buckets;
+  return {
+    observe(labels, value) {
+      if (value === undefined || value === null) return;
+      const key = labelString(labels);
+      const series = metric.series.get(key) ||
+        { labels, counts: buckets.map(() => 0), sum: 0, count: 0 };
+      buckets.forEach((bucket, i) => { if (value <= bucket) series.counts[i]++; });
+      series.sum += value;
+      series.count++;
+      metric.series.set(key, series);
+      emit('histogram', name, labels, value);
+    }
+  };
+}
+
+function renderHistogram(metric, lines) {
+  metric.series.forEach((series, key) => {
+    metric.buckets.forEach((bucket, i) => {
+      lines.push(`${metric.name}_bucket${labelString({ ...series.labels, le: bucket })} ${series.counts[i]}`);
+    });
+    lines.push(`${metric.name}_bucket${labelString({ ...series.labels, le: '+Inf' })} ${series.count}`);
+    lines.push(`${metric.name}_sum${key} ${series.sum}`);
+    lines.push(`${metric.name}_count${key} ${series.count}`);
+  });
+}
+
+export function renderMetrics() {
+  const lines = [];
+  metrics.forEach((metric) => {
+    // collect() returns [{ labels, value }] for series computed on scrape
+    if (metric.collect) {
+      metric.series.clear();
+      metric.collect().forEach(({ labels, value }) => {
+        if (value !== undefined && value !== null) metric.series.set(labelString(labels), value);
+      });
+    }
+
+    lines.push(`# HELP ${metric.name} ${metric.help}`);
+    lines.push(`# TYPE ${metric.name} ${metric.type}`);
+    if (metric.type === 'histogram') {
+      renderHistogram(metric, lines);
+    } else {
+      metric.series.forEach((value, key) => lines.push(`${metric.name}${key} ${value}`));
+    }
+  });
+  return lines.join('\n') + '\n';
+}
@@ -0,0 +1,11 @@
+// Small helpers shared by the ping loop and the background watchers.
+
+export const sleep = (ms) => new Promise(r => setTimeout(r, ms));
+
+// Prefix log lines with an ISO timestamp like the rest of the script.
+export const timestamp = () => new Date().toISOString();
+
+// Exponential backoff with full jitter for retrying after `failures`
+// consecutive errors, capped at maxMs.
+export const backoffMs = (failures, baseMs, maxMs = 60000) =>
+  Math.random() * Math.min(maxMs, Number(baseMs) * 2 ** failures);
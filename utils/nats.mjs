@@ -0,0 +1,63 @@
+// Publish ping events to NATS, on <subjectPrefix>.<event type>. This speaks
+// the plain text NATS protocol over TCP (no TLS), which is all a publisher
+// needs. Events raised while disconnected are dropped, since they're meant
+// for live dashboards.
+import net from 'net';
+import { timestamp } from './misc.mjs';
+
+let socket;
+let subjectPrefix = 'ping_thing';
+
+export function natsSink(prefix) {
+  subjectPrefix = prefix;
+  return (event) => {
+    if (!socket) return;
+    const payload = JSON.stringify(event);
+    socket.write(`PUB ${subjectPrefix}.${event.type} ${Buffer.byteLength(payload)}\r\n${payload}\r\n`);
+  };
+}
+
+// url is nats://[user:pass@|token@]host:port. Resolves or rejects when the
+// connection is lost.
+export function runNatsPublisher(url, name) {
+  const { hostname, port, username, password } = new URL(url);
+  const connect = { verbose: false, pedantic: false, name: name, lang: 'node', version: '1.0' };
+  if (username && password) {
+    connect.user = decodeURIComponent(username);
+    connect.pass = decodeURIComponent(password);
+  } else if (username) {
+    connect.auth_token = decodeURIComponent(username);
+  }
+
+  return new Promise((resolve, reject) => {
+    const conn = net.createConnection({ host: hostname, port: Number(port || 4222) });
+    let buffer = '';
+    conn.setEncoding('utf8');
+    conn.on('data', (data) => {
+      buffer += data;
+      let end;
+      while ((end = buffer.indexOf('\r\n')) !== -1) {
+        const line = buffer.slice(0, end);
+        buffer = buffer.slice(end + 2);
+        if (line.startsWith('INFO')) {
+          conn.write(`CONNECT ${JSON.stringify(connect)}\r\nPING\r\n`);
+        } else if (line === 'PING') {
+          conn.write('PONG\r\n');
+        } else if (line === 'PONG' && !socket) {
+          socket = conn;
+          console.log(`${timestamp()} Connected to NATS at ${hostname}`);
+        } else if (line.startsWith('-ERR')) {
+          console.log(`${timestamp()} ERROR: NATS: ${line}`);
+        }
+      }
+    });
+    conn.on('error', (e) => {
+      socket = undefined;
+      reject(e);
+    });
+    conn.on('close', () => {
+      socket = undefined;
+      resolve();
+    });
+  });
+}
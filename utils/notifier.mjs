@@ -0,0 +1,61 @@
+// Post alerts to chat. Each alert has a key (e.g. timeouts, watcher:slot)
+// and is sent at most once per minIntervalMs per key. Alerts dropped by
+// the rate limit are counted and mentioned in the next one sent for that
+// key, so an alert storm becomes a trickle of summaries.
+import { timestamp } from './misc.mjs';
+
+const POST_TIMEOUT_MS = 5000;
+
+// Chat services, each turning an alert line into the request body its
+// webhook expects
+const CHANNELS = {
+  slack: (text) => ({ text: text }),
+  discord: (text) => ({ content: text }),
+  telegram: (text, channel) => ({ chat_id: channel.chatId, text: text })
+};
+
+let notifierConfig = { channels: [] };
+
+// key => { sentAt, suppressed }
+const alertState = new Map();
+
+// channels is [{ type: 'slack' | 'discord' | 'telegram', url }]. Telegram
+// channels also need a chatId.
+export function configureNotifier({ channels, pingerName, minIntervalMs }) {
+  notifierConfig = { channels, pingerName, minIntervalMs };
+}
+
+function post(channel, text) {
+  return fetch(channel.url, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify(CHANNELS[channel.type](text, channel)),
+    signal: AbortSignal.timeout(POST_TIMEOUT_MS)
+  }).then((response) => {
+    if (!response.ok) throw new Error(`HTTP ${response.status}`);
+  });
+}
+
+export function notify(key, message) {
+  if (notifierConfig.channels.length === 0) return;
+
+  const state = alertState.get(key) || { sentAt: 0, suppressed: 0 };
+  alertState.set(key, state);
+  if (Date.now() - state.sentAt < notifierConfig.minIntervalMs) {
+    state.suppressed++;
+    return;
+  }
+
+  const suppressed = state.suppressed
+    ? ` (${state.suppressed} similar alerts suppressed)`
+    : '';
+  const text = `[${notifierConfig.pingerName}] ${message}${suppressed}`;
+  state.sentAt = Date.now();
+  state.suppressed = 0;
+
+  notifierConfig.channels.forEach((channel) => {
+    post(channel, text).catch((e) => {
+      console.log(`${timestamp()} ERROR: Unable to send ${channel.type} alert: ${e.message}`);
+    });
+  });
+}
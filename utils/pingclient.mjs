@@ -0,0 +1,236 @@
+// Ping measurement as a library, for tools that want to embed pings instead
+// of running ping-thing-client.mjs. It runs the core cycle: fetch a
+// blockhash, send a self transfer through one or more endpoints, confirm it
+// and classify what happened, with the same steps as the client script (see
+// pingcycle.mjs). The script adds tracing, metrics, resends, experiments and
+// its sinks on top.
+//
+//   import { PingClientBuilder } from 'ping-thing-client';
+//
+//   const client = new PingClientBuilder()
+//     .endpoint('https://rpc.example')
+//     .keypair(keypair)
+//     .feeStrategy(10000)
+//     .reporter({ name: 'log', report: ({ record }) => console.log(record) })
+//     .build();
+//   const result = await client.pingOnce();
+//
+// Results have the fields of the payload sent to validators.app, plus
+// tx_signature, sent_at, error and error_category. Reporters are the same
+// { name, report({ result, record, span }) } objects the script uses (see
+// reporters.mjs), so its sinks work here too: result is the payload and
+// record the whole result.
+import bs58 from 'bs58';
+import { sleep, timestamp } from './misc.mjs';
+import { rpcConnection } from './rpcheaders.mjs';
+import { createSendConnections, sendToAll } from './fanout.mjs';
+import { fetchLandedSlot } from './confirm.mjs';
+import { confirmPing, classifyTimeout, pingPayload } from './pingcycle.mjs';
+import { COMMITMENT_LEVELS } from './deadline.mjs';
+import { buildPingTransaction } from './transaction.mjs';
+import { classifyPingError } from './errors.mjs';
+import { reportPing } from './reporters.mjs';
+import { startTrace } from './tracing.mjs';
+
+export class PingClientBuilder {
+  constructor() {
+    this.options = {
+      sendEndpoints: [],
+      commitment: 'confirmed',
+      computeUnitLimit: 1000,
+      pollMs: 2000,
+      sleepMs: 5000,
+      reporters: []
+    };
+  }
+
+  // The RPC endpoint used for blockhashes and confirmation, and for sending
+  // unless sendEndpoints is set
+  endpoint(url) {
+    this.options.endpoint = url;
+    return this;
+  }
+
+  // Send every ping through all of these endpoints at once
+  sendEndpoints(urls) {
+    this.options.sendEndpoints = urls;
+    return this;
+  }
+
+  // The web3.js Keypair that pays for and signs the pings
+  keypair(keypair) {
+    this.options.keypair = keypair;
+    return this;
+  }
+
+  // One of processed, confirmed or finalized
+  commitment(commitment) {
+    this.options.commitment = commitment;
+    return this;
+  }
+
+  // A fixed priority fee in micro-lamports per CU, or a fee provider from
+  // createPriorityFeeProvider asked for the given percentile before each
+  // ping. Without one pings go without a priority fee.
+  feeStrategy(strategy, percentile) {
+    this.options.feeStrategy = strategy;
+    this.options.percentile = percentile;
+    return this;
+  }
+
+  computeUnitLimit(limit) {
+    this.options.computeUnitLimit = limit;
+    return this;
+  }
+
+  // Give up on confirmation after ms, instead of when the blockhash expires
+  confirmationTimeoutMs(ms) {
+    this.options.confirmationTimeoutMs = ms;
+    return this;
+  }
+
+  // How often run() pings
+  sleepMs(ms) {
+    this.options.sleepMs = ms;
+    return this;
+  }
+
+  // A reporter to hand every result to, e.g. from createReporters
+  reporter(reporter) {
+    if (typeof (reporter && reporter.report) !== 'function') {
+      throw new Error('reporter must be an object with a report function');
+    }
+    this.options.reporters.push(reporter);
+    return this;
+  }
+
+  // Throws if a required option is missing or invalid
+  build() {
+    const { endpoint, keypair, commitment, feeStrategy } = this.options;
+    if (!endpoint) throw new Error('PingClientBuilder needs an endpoint');
+    if (!keypair) throw new Error('PingClientBuilder needs a keypair');
+    if (!COMMITMENT_LEVELS.includes(commitment)) {
+      throw new Error(`commitment must be one of ${COMMITMENT_LEVELS.join(', ')}`);
+    }
+    if (
+      feeStrategy !== undefined &&
+      !Number.isFinite(feeStrategy) &&
+      typeof (feeStrategy && feeStrategy.fetch) !== 'function'
+    ) {
+      throw new Error('feeStrategy must be a number or a fee provider');
+    }
+    return new PingClient({ ...this.options, reporters: [...this.options.reporters] });
+  }
+}
+
+export class PingClient {
+  constructor(options) {
+    this.options = options;
+    this.connection = rpcConnection(options.endpoint, options.commitment);
+    this.sendConnections = createSendConnections(
+      options.sendEndpoints.length ? options.sendEndpoints : [options.endpoint],
+      options.commitment
+    );
+    this.stopped = false;
+  }
+
+  // { fee, strategy } for the next ping
+  async priorityFee() {
+    const { feeStrategy, percentile } = this.options;
+    if (feeStrategy === undefined) return { fee: undefined, strategy: undefined };
+    if (Number.isFinite(feeStrategy)) return { fee: feeStrategy, strategy: 'fixed' };
+    try {
+      return await feeStrategy.fetch(percentile);
+    } catch (e) {
+      console.log(`${timestamp()} ERROR: Unable to fetch priority fee: ${e.message}`);
+      return { fee: undefined, strategy: undefined };
+    }
+  }
+
+  // Send one ping and resolve with its result once it confirmed or failed.
+  // Only rejects if no blockhash could be fetched.
+  async pingOnce() {
+    const { keypair, commitment, computeUnitLimit, pollMs, confirmationTimeoutMs } = this.options;
+    const latestBlockhash = await this.connection.getLatestBlockhash(commitment);
+    const { fee, strategy } = await this.priorityFee();
+    const tx = buildPingTransaction(keypair, fee, computeUnitLimit, 0);
+    tx.recentBlockhash = latestBlockhash.blockhash;
+    tx.lastValidBlockHeight = latestBlockhash.lastValidBlockHeight;
+    tx.sign(keypair);
+    const txSignature = bs58.encode(tx.signature);
+    const rawTransaction = tx.serialize();
+
+    const span = startTrace('ping', { 'signature': txSignature, 'commitment': commitment });
+    const txStart = Date.now();
+    let sent = false;
+    let sendMs;
+    let success = false;
+    let reason;
+    let error;
+    let errorCategory;
+    try {
+      await sendToAll(this.sendConnections, rawTransaction, { preflightCommitment: commitment });
+      sent = true;
+      sendMs = Date.now() - txStart;
+      await confirmPing(this.connection, txSignature, latestBlockhash, commitment, pollMs, confirmationTimeoutMs);
+      success = true;
+    } catch (e) {
+      error = e.message;
+      errorCategory = classifyPingError(e, sent);
+      if (errorCategory === 'on_chain_error') {
+        reason = 'on_chain_error';
+      } else if (errorCategory === 'timeout') {
+        reason = await classifyTimeout(this.connection, commitment, tx.lastValidBlockHeight)
+          .catch(() => 'dropped');
+      } else {
+        reason = errorCategory;
+      }
+    }
+    const time = Date.now() - txStart;
+    const slotLanded = success
+      ? await fetchLandedSlot(this.connection, txSignature).catch(() => undefined)
+      : undefined;
+
+    const payload = pingPayload(txSignature, {
+      time: time,
+      transaction_type: 'transfer',
+      success: success,
+      commitment_level: commitment,
+      reason: reason,
+      slot_landed: slotLanded,
+      priority_fee_micro_lamports: fee,
+      priority_fee_strategy: strategy,
+      compute_unit_limit: computeUnitLimit,
+      tx_size: rawTransaction.length,
+      send_ms: sendMs
+    });
+    const result = {
+      ...payload,
+      tx_signature: txSignature,
+      sent_at: new Date(txStart).toISOString(),
+      error: error,
+      error_category: errorCategory
+    };
+    reportPing(this.options.reporters, { result: payload, record: result, span: span });
+    span.setAttributes({ 'success': success, 'reason': reason });
+    span.end();
+    return result;
+  }
+
+  // Ping every sleepMs until stop() is called
+  async run() {
+    this.stopped = false;
+    while (!this.stopped) {
+      try {
+        await this.pingOnce();
+      } catch (e) {
+        console.log(`${timestamp()} ERROR: Unable to obtain a new blockhash: ${e.message}`);
+      }
+      if (!this.stopped) await sleep(this.options.sleepMs);
+    }
+  }
+
+  stop() {
+    this.stopped = true;
+  }
+}
@@ -0,0 +1,73 @@
+// The parts of a ping that ping-thing-client.mjs and the PingClient library
+// share: confirming the TX, telling expired from dropped TXs and building
+// the payload validators.app takes.
+import { confirmSignature } from './confirm.mjs';
+
+// A TX that never landed has no signature to show. validators.app takes
+// this instead to pass its import filters.
+export const FAKE_SIGNATURE = '9'.repeat(88);
+
+// Confirm the ping's TX like confirmSignature, but throw a
+// TransactionFailedError if it landed and failed
+export async function confirmPing(connection, signature, latestBlockhash, commitment, pollMs, deadlineMs) {
+  const result = await confirmSignature(connection, signature, latestBlockhash, commitment, pollMs, deadlineMs);
+  if (result.value.err) {
+    const error = new Error(`Transaction ${signature} failed (${JSON.stringify(result.value)})`);
+    error.name = 'TransactionFailedError';
+    throw error;
+  }
+  return result;
+}
+
+// Classify a TX that timed out. If the cluster's block height has moved past
+// the blockhash's lastValidBlockHeight, the TX can never land and it expired.
+// Otherwise the blockhash was still valid when we gave up, so the TX was
+// simply never included (dropped). cachedBlockHeight saves the RPC call when
+// a fresh one is at hand.
+export async function classifyTimeout(connection, commitment, lastValidBlockHeight, cachedBlockHeight) {
+  if (lastValidBlockHeight === undefined) return 'dropped';
+  const blockHeight = cachedBlockHeight === undefined
+    ? await connection.getBlockHeight(commitment)
+    : cachedBlockHeight;
+  return blockHeight > lastValidBlockHeight ? 'expired' : 'dropped';
+}
+
+// The payload sent to validators.app for a ping. fields holds the payload
+// fields the caller knows; the signature comes from txSignature if the TX
+// landed and the CU limit and fee strategy only go with a priority fee.
+export function pingPayload(txSignature, fields) {
+  const landed = fields.success || fields.reason === 'on_chain_error';
+  const priorityFee = fields.priority_fee_micro_lamports;
+  return {
+    time: fields.time,
+    signature: landed ? txSignature : FAKE_SIGNATURE,
+    transaction_type: fields.transaction_type,
+    success: fields.success,
+    application: 'web3',
+    commitment_level: fields.commitment_level,
+    // Why the ping failed. e.g. expired, dropped or on_chain_error
+    reason: fields.reason,
+    resends: fields.resends,
+    processed_time: fields.processed_time,
+    confirmed_time: fields.confirmed_time,
+    finalized_time: fields.finalized_time,
+    slot_sent: fields.slot_sent,
+    slot_landed: fields.slot_landed,
+    leader_sent: fields.leader_sent,
+    leader_landed: fields.leader_landed,
+    slots_to_next_leader: fields.slots_to_next_leader,
+    priority_fee_micro_lamports: priorityFee,
+    priority_fee_percentile: priorityFee === undefined ? undefined : fields.priority_fee_percentile,
+    // How the fee was picked, e.g. rpc:p75 or helius:Medium
+    priority_fee_strategy: priorityFee === undefined ? undefined : fields.priority_fee_strategy,
+    compute_unit_limit: priorityFee === undefined ? undefined : fields.compute_unit_limit,
+    tx_size: fields.tx_size,
+    compute_units_consumed: fields.compute_units_consumed,
+    fee_lamports: fields.fee_lamports,
+    tx_index: fields.tx_index,
+    // Baseline round-trip time to RPC_ENDPOINT, the network's share of time
+    rpc_rtt_ms: fields.rpc_rtt_ms,
+    send_ms: fields.send_ms,
+    inclusion_ms: fields.inclusion_ms
+  };
+}
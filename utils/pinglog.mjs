@@ -0,0 +1,12 @@
+// Append every ping to a local JSONL file, one JSON object per line, so the
+// raw data survives whatever happens to VA or the metrics pipeline.
+import fs from 'fs';
+import { timestamp } from './misc.mjs';
+
+export function appendPingRecord(path, record) {
+  try {
+    fs.appendFileSync(path, JSON.stringify(record) + '\n');
+  } catch (e) {
+    console.log(`${timestamp()} ERROR: Unable to write ping log ${path}: ${e.message}`);
+  }
+}
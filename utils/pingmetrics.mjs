@@ -0,0 +1,346 @@
+// The Prometheus metrics exported by the ping client, served on /metrics.
+import { addRoute } from './server.mjs';
+import { counter, gauge, histogram, renderMetrics } from './metrics.mjs';
+import { blockHeightLagMs } from './blockheight.mjs';
+import { globalSlot } from './slot.mjs';
+import { globalRtt } from './rtt.mjs';
+import { rateLimit } from './ratelimit.mjs';
+import { activeSendEndpoint } from './failover.mjs';
+import { watcherRestarts } from './supervisor.mjs';
+import { pingState } from './health.mjs';
+import { lateLandingQueueSize } from './latelanding.mjs';
+import { trackedLeaderCount, topLeaderStats } from './leaderstats.mjs';
+import { globalPriorityFees } from './priorityfees.mjs';
+import { globalBalance } from './balance.mjs';
+import { walletRunway } from './runway.mjs';
+import { feeBudget } from './feebudget.mjs';
+import { latestSummary, SUMMARY_QUANTILES } from './summary.mjs';
+import { landingAlarm } from './landingalarm.mjs';
+import { vaQueueSize } from './vaqueue.mjs';
+import { vaBatchSize } from './vabatch.mjs';
+import { clickhouseBufferSize } from './clickhouse.mjs';
+import { kafkaBufferSize } from './kafka.mjs';
+
+export const confirmationLatencyMs = histogram(
+  'ping_thing_confirmation_latency_ms',
+  'Time from sending a ping until it reached the commitment level'
+);
+
+// With MEASURE_ALL_COMMITMENTS, every level a successful ping reached
+export const commitmentLatencyMs = histogram(
+  'ping_thing_commitment_latency_ms',
+  'Time from sending a ping until it reached each commitment level'
+);
+
+// With MEASURE_ALL_COMMITMENTS, how long optimistic confirmation took after
+// a ping was processed
+export const processedToConfirmedMs = histogram(
+  'ping_thing_processed_to_confirmed_ms',
+  'Time from a ping reaching processed until it reached confirmed',
+  [50, 100, 200, 400, 800, 1600, 3200, 6400, 12800]
+);
+
+// With FETCH_TX_DETAILS, to compare against the requested compute unit limit
+export const computeUnitsConsumed = histogram(
+  'ping_thing_compute_units_consumed',
+  'Compute units used by landed pings',
+  [150, 300, 450, 600, 1000, 2000, 5000, 10000, 50000, 200000]
+);
+
+// phase is send (until the sendTransaction response), propagation (from
+// then until the landing slot started) or inclusion (from then until
+// confirmed)
+export const latencyPhaseMs = histogram(
+  'ping_thing_latency_phase_ms',
+  'Confirmation latency of landed pings split into send, propagation and inclusion'
+);
+
+// outcome is one of confirmed, expired, dropped, on_chain_error, send_error
+// or error
+export const pingsTotal = counter(
+  'ping_thing_pings_total',
+  'Pings by outcome'
+);
+
+// category is one of ERROR_CATEGORIES in errors.mjs
+export const pingErrorsTotal = counter(
+  'ping_thing_ping_errors_total',
+  'Failed pings by error category'
+);
+
+export const transactionsSentTotal = counter(
+  'ping_thing_transactions_sent_total',
+  'Pings accepted by at least one RPC endpoint'
+);
+
+export const resendsTotal = counter(
+  'ping_thing_resends_total',
+  'TX resend attempts'
+);
+
+export const confirmationTimeoutsTotal = counter(
+  'ping_thing_confirmation_timeouts_total',
+  'Pings that were not confirmed in time, by category (expired or dropped)'
+);
+
+// outcome is landed (within LATE_LANDING_GRACE_MS) or dropped
+export const lateLandingsTotal = counter(
+  'ping_thing_late_landings_total',
+  'Timed out pings that landed late or were finally given up as dropped'
+);
+
+export const failedTransactionsTotal = counter(
+  'ping_thing_failed_transactions_total',
+  'Pings that landed but failed on chain'
+);
+
+// source is meta (from getTransaction, with FETCH_TX_DETAILS) or estimate
+// (signature fee plus priority fee on the CU limit)
+export const feesPaidLamportsTotal = counter(
+  'ping_thing_fees_paid_lamports_total',
+  'Lamports paid in fees by landed pings'
+);
+
+export const vaSubmissionErrorsTotal = counter(
+  'ping_thing_va_submission_errors_total',
+  'Failed POSTs to validators.app'
+);
+
+// kind is send or resend, outcome is ok or error
+export const sendLatencyMs = histogram(
+  'ping_thing_send_latency_ms',
+  'sendTransaction round-trip time per RPC endpoint, provider and region',
+  [10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000]
+);
+
+gauge(
+  'ping_thing_block_height_lag_ms',
+  'Age of the cached block height',
+  () => [{ labels: {}, value: blockHeightLagMs() }]
+);
+
+const ageMs = (updatedAt) => updatedAt === undefined ? undefined : Date.now() - updatedAt;
+
+gauge(
+  'ping_thing_slot_age_ms',
+  'Time since the last slot update',
+  () => [{ labels: {}, value: ageMs(globalSlot.updatedAt) }]
+);
+
+gauge(
+  'ping_thing_slot_feed_lag_slots',
+  'How many slots the slot subscription was behind getSlot at the last check',
+  () => [{ labels: {}, value: globalSlot.lagSlots }]
+);
+
+gauge(
+  'ping_thing_rpc_rtt_ms',
+  'Baseline round-trip time to the RPC endpoint (fastest getVersion)',
+  () => [{ labels: {}, value: globalRtt.ms }]
+);
+
+gauge(
+  'ping_thing_blockhash_age_ms',
+  'Time from fetching the latest ping blockhash to sending the TX that uses it',
+  () => [{ labels: {}, value: pingState.blockhashAgeMs }]
+);
+
+gauge(
+  'ping_thing_priority_fee_micro_lamports',
+  'Priority fee currently added to pings, per compute unit',
+  () => [{ labels: {}, value: globalPriorityFees.fee }]
+);
+
+gauge(
+  'ping_thing_priority_fee_raw_micro_lamports',
+  'Latest priority fee from the fee source, before smoothing',
+  () => [{ labels: {}, value: globalPriorityFees.rawFee }]
+);
+
+counter(
+  'ping_thing_priority_fee_clamps_total',
+  'Fee estimates raised to PRIORITY_FEE_MIN or cut to PRIORITY_FEE_MAX',
+  () => Object.entries(globalPriorityFees.clamps)
+    .map(([bound, count]) => ({ labels: { bound }, value: count }))
+);
+
+gauge(
+  'ping_thing_priority_fee_percentile',
+  'Percentile of recent prioritization fees used for pings',
+  () => [{ labels: {}, value: globalPriorityFees.percentile }]
+);
+
+gauge(
+  'ping_thing_wallet_balance_lamports',
+  'Balance of each pinging wallet',
+  () => Object.entries(globalBalance.wallets)
+    .map(([wallet, lamports]) => ({ labels: { wallet }, value: lamports }))
+);
+
+gauge(
+  'ping_thing_wallet_runway_hours',
+  'Hours until each wallet runs dry at its recent spend rate',
+  () => Object.entries(walletRunway.hours)
+    .map(([wallet, hours]) => ({ labels: { wallet }, value: hours }))
+);
+
+gauge(
+  'ping_thing_ping_cost_lamports',
+  'Average fee paid per ping by each wallet over the runway window',
+  () => Object.entries(walletRunway.lamportsPerPing)
+    .map(([wallet, lamports]) => ({ labels: { wallet }, value: lamports }))
+);
+
+gauge(
+  'ping_thing_daily_fees_lamports',
+  'Fees paid by pings since 00:00 UTC',
+  () => [{ labels: {}, value: feeBudget.spentLamports }]
+);
+
+gauge(
+  'ping_thing_fee_budget_exhausted',
+  '1 while pings are paused because MAX_DAILY_FEE_LAMPORTS was reached',
+  () => [{ labels: {}, value: feeBudget.exhausted ? 1 : 0 }]
+);
+
+gauge(
+  'ping_thing_process_rss_bytes',
+  'Resident set size of the ping client',
+  () => [{ labels: {}, value: process.memoryUsage().rss }]
+);
+
+gauge(
+  'ping_thing_late_landing_queue_size',
+  'Timed out TXs still being watched for a late landing',
+  () => [{ labels: {}, value: lateLandingQueueSize() }]
+);
+
+gauge(
+  'ping_thing_va_queue_size',
+  'validators.app submissions waiting to be retried',
+  () => [{ labels: {}, value: vaQueueSize() }]
+);
+
+gauge(
+  'ping_thing_va_batch_size',
+  'Ping results waiting for the next batched validators.app submission',
+  () => [{ labels: {}, value: vaBatchSize() }]
+);
+
+gauge(
+  'ping_thing_clickhouse_buffer_size',
+  'Pings waiting to be inserted into ClickHouse',
+  () => [{ labels: {}, value: clickhouseBufferSize() }]
+);
+
+gauge(
+  'ping_thing_kafka_buffer_size',
+  'Ping events waiting to be published to Kafka',
+  () => [{ labels: {}, value: kafkaBufferSize() }]
+);
+
+gauge(
+  'ping_thing_leader_stats_leaders',
+  'Leaders held in the rolling per-leader stats',
+  () => [{ labels: {}, value: trackedLeaderCount() }]
+);
+
+gauge(
+  'ping_thing_summary_latency_ms',
+  'Confirmation latency quantiles over the last summary interval',
+  () => latestSummary.latencyMs
+    ? SUMMARY_QUANTILES.map((q) => ({
+        labels: { quantile: String(q / 100) },
+        value: latestSummary.latencyMs[`p${q}`]
+      }))
+    : []
+);
+
+gauge(
+  'ping_thing_summary_landing_rate',
+  'Share of pings that landed over the last summary interval',
+  () => [{ labels: {}, value: latestSummary.landingRate }]
+);
+
+gauge(
+  'ping_thing_summary_median_slot_latency',
+  'Median slots from send to landing over the last summary interval',
+  () => [{ labels: {}, value: latestSummary.medianSlotLatency }]
+);
+
+gauge(
+  'ping_thing_landing_rate',
+  'Share of the pings in the landing rate alarm window that confirmed in time',
+  () => [{ labels: {}, value: landingAlarm.rate }]
+);
+
+gauge(
+  'ping_thing_landing_rate_alarm',
+  '1 while the landing rate is below the alarm threshold',
+  () => [{ labels: {}, value: landingAlarm.firing ? 1 : 0 }]
+);
+
+counter(
+  'ping_thing_slot_stale_events_total',
+  'Times the slot subscription went quiet and was replaced',
+  () => [{ labels: {}, value: globalSlot.staleEvents }]
+);
+
+counter(
+  'ping_thing_rate_limited_total',
+  'RPC rate limit (429) responses by polling task',
+  () => Object.entries(rateLimit.hits)
+    .map(([task, value]) => ({ labels: { task }, value }))
+);
+
+gauge(
+  'ping_thing_rate_limit_pause_ms',
+  'Time left until RPC polling resumes after a rate limit',
+  () => [{ labels: {}, value: Math.max(0, rateLimit.limitedUntil - Date.now()) }]
+);
+
+gauge(
+  'ping_thing_active_send_endpoint',
+  'Send endpoint in use in failover mode, set to 1',
+  () => (activeSendEndpoint.label === undefined ? [] : [{
+    labels: { endpoint: activeSendEndpoint.label, provider: activeSendEndpoint.provider },
+    value: 1
+  }])
+);
+
+counter(
+  'ping_thing_watcher_restarts_total',
+  'Background watcher restarts by the supervisor',
+  () => Object.entries(watcherRestarts)
+    .map(([watcher, value]) => ({ labels: { watcher }, value }))
+);
+
+// Per-leader stats over each leader's last LEADER_STATS_WINDOW pings, for the
+// LEADER_STATS_METRICS_COUNT leaders with the most pings
+gauge(
+  'ping_thing_leader_pings',
+  'Pings sent while the leader was producing, in the rolling window',
+  () => topLeaderStats().map((s) => ({ labels: { leader: s.leader }, value: s.pings }))
+);
+
+gauge(
+  'ping_thing_leader_landing_rate',
+  'Share of the pings sent during the leader\'s slots that landed',
+  () => topLeaderStats().map((s) => ({ labels: { leader: s.leader }, value: s.landingRate }))
+);
+
+gauge(
+  'ping_thing_leader_median_slot_latency',
+  'Median slots from send to landing for pings sent during the leader\'s slots',
+  () => topLeaderStats()
+    .filter((s) => s.medianSlotLatency !== undefined)
+    .map((s) => ({ labels: { leader: s.leader }, value: s.medianSlotLatency }))
+);
+
+export function addMetricsRoute() {
+  addRoute('/metrics', () => ({
+    status: 200,
+    contentType: 'text/plain; version=0.0.4',
+    body: renderMetrics()
+  }));
+}
@@ -0,0 +1,63 @@
+// /pings/recent and /pings/stats: the latest ping records and aggregates
+// over a time window, straight from memory.
+//   /pings/recent?limit=50
+//   /pings/stats?window_ms=3600000
+import { addRoute, jsonResponse } from './server.mjs';
+import { median } from './leaderstats.mjs';
+import { percentileOf } from './priorityfees.mjs';
+
+let recent = [];
+let maxRecent = 1000;
+
+export function recordRecentPing(record) {
+  recent.push(record);
+  if (recent.length > maxRecent) recent = recent.slice(-maxRecent);
+}
+
+export function pingStats(windowMs) {
+  const since = new Date(Date.now() - windowMs).toISOString();
+  const pings = recent.filter((p) => p.sent_at >= since);
+  const landed = pings.filter((p) => p.success);
+  const latencies = landed.map((p) => p.time);
+  const slotLatencies = landed
+    .filter((p) => p.slot_landed !== undefined && p.slot_sent !== undefined)
+    .map((p) => p.slot_landed - p.slot_sent);
+  const fees = pings
+    .map((p) => p.priority_fee_micro_lamports)
+    .filter((f) => f !== undefined);
+  const reasons = {};
+  pings.filter((p) => p.reason).forEach((p) => {
+    reasons[p.reason] = (reasons[p.reason] || 0) + 1;
+  });
+
+  return {
+    window_ms: windowMs,
+    pings: pings.length,
+    landed: landed.length,
+    landing_rate: pings.length ? landed.length / pings.length : null,
+    latency_ms: {
+      p50: percentileOf(latencies, 50) ?? null,
+      p90: percentileOf(latencies, 90) ?? null,
+      p99: percentileOf(latencies, 99) ?? null
+    },
+    median_slot_latency: median(slotLatencies) ?? null,
+    median_priority_fee_micro_lamports: median(fees) ?? null,
+    failures_by_reason: reasons
+  };
+}
+
+const queryNumber = (req, name, fallback) => {
+  const value = Number(new URL(req.url, 'http://localhost').searchParams.get(name));
+  return value > 0 ? value : fallback;
+};
+
+// Keep the last `max` pings in memory for the routes
+export function addPingRoutes(max) {
+  maxRecent = max;
+  addRoute('/pings/recent', (req) =>
+    jsonResponse(200, recent.slice(-queryNumber(req, 'limit', 50)).reverse())
+  );
+  addRoute('/pings/stats', (req) =>
+    jsonResponse(200, pingStats(queryNumber(req, 'window_ms', 3600000)))
+  );
+}
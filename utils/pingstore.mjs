@@ -0,0 +1,28 @@
+// Read back the ping records written to PING_SQLITE_DB or PING_LOG_FILE, for
+// the analyze and export-research commands.
+import fs from 'fs';
+import { openSqlite, querySqlite } from './sqlite.mjs';
+
+// Pings sent at or after sinceMs, oldest first, as ping records. SQLite
+// wins if both are set. success is a boolean either way.
+export async function loadPingRecords(env, sinceMs = 0) {
+  const since = new Date(sinceMs).toISOString();
+  let records;
+  if (env.PING_SQLITE_DB) {
+    // Brings a database from an older version up to date
+    openSqlite(env.PING_SQLITE_DB);
+    records = await querySqlite(
+      env.PING_SQLITE_DB,
+      `SELECT * FROM pings WHERE sent_at >= '${since}' ORDER BY sent_at`
+    );
+  } else if (env.PING_LOG_FILE) {
+    records = fs.readFileSync(env.PING_LOG_FILE, 'utf8')
+      .split('\n')
+      .filter((line) => line.trim())
+      .map((line) => JSON.parse(line))
+      .filter((record) => record.sent_at >= since);
+  } else {
+    throw new Error('Reading stored pings needs PING_SQLITE_DB or PING_LOG_FILE');
+  }
+  return records.map((record) => ({ ...record, success: Boolean(record.success) }));
+}
@@ -0,0 +1,76 @@
+// Priority fees. A background watcher polls a fee provider (see
+// feeproviders.mjs) and keeps the latest estimate, which the ping loop adds
+// to each TX as a compute unit price.
+import web3 from '@solana/web3.js';
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+import { rateLimitedDelay, noteRateLimit } from './ratelimit.mjs';
+
+const COMPUTE_BUDGET_PROGRAM_ID = new web3.PublicKey(
+  'ComputeBudget111111111111111111111111111111'
+);
+
+// Shared state. fee is in micro-lamports per compute unit. rawFee is the
+// fee before smoothing and clamping, if any. clamps counts the estimates
+// raised to PRIORITY_FEE_MIN or cut to PRIORITY_FEE_MAX.
+export const globalPriorityFees = {
+  fee: undefined,
+  rawFee: undefined,
+  strategy: undefined,
+  source: undefined,
+  percentile: undefined,
+  clamps: { min: 0, max: 0 },
+  updatedAt: undefined
+};
+
+// Nearest-rank percentile of a list of numbers.
+export function percentileOf(values, percentile) {
+  if (values.length === 0) return undefined;
+  const sorted = [...values].sort((a, b) => a - b);
+  const rank = Math.ceil((percentile / 100) * sorted.length);
+  return sorted[Math.min(sorted.length - 1, Math.max(0, rank - 1))];
+}
+
+// provider is from createPriorityFeeProvider. percentile is the starting
+// percentile for providers that use one.
+export async function watchPriorityFees(provider, percentile, intervalMs) {
+  globalPriorityFees.source = provider.name;
+  // A reload may already have changed the percentile
+  if (globalPriorityFees.percentile === undefined && provider.usesPercentile) {
+    globalPriorityFees.percentile = Number(percentile);
+  }
+  let failures = 0;
+  while (true) {
+    try {
+      const estimate = await provider.fetch(globalPriorityFees.percentile);
+      globalPriorityFees.fee = estimate.fee;
+      globalPriorityFees.rawFee = estimate.rawFee === undefined ? estimate.fee : estimate.rawFee;
+      globalPriorityFees.strategy = estimate.strategy;
+      if (estimate.clamped) globalPriorityFees.clamps[estimate.clamped]++;
+      globalPriorityFees.updatedAt = Date.now();
+      failures = 0;
+    } catch (e) {
+      failures++;
+      noteRateLimit('priority_fees', e);
+      console.log(`${timestamp()} ERROR: Unable to fetch priority fees (${failures} in a row): ${e.message}`);
+    }
+    await sleep(rateLimitedDelay(failures ? backoffMs(failures, intervalMs) : intervalMs));
+  }
+}
+
+// SetComputeUnitLimit & SetComputeUnitPrice, encoded by hand because our
+// web3.js version predates ComputeBudgetProgram.setComputeUnitPrice.
+export function computeBudgetInstructions(microLamports, unitLimit) {
+  const limitData = Buffer.alloc(5);
+  limitData.writeUInt8(2, 0);
+  limitData.writeUInt32LE(Number(unitLimit), 1);
+
+  const priceData = Buffer.alloc(9);
+  priceData.writeUInt8(3, 0);
+  priceData.writeBigUInt64LE(BigInt(microLamports), 1);
+
+  return [limitData, priceData].map((data) => new web3.TransactionInstruction({
+    keys: [],
+    programId: COMPUTE_BUDGET_PROGRAM_ID,
+    data: data
+  }));
+}
@@ -0,0 +1,60 @@
+// Run several cluster profiles (e.g. mainnet and testnet) from one service.
+// Each CLUSTER_PROFILES entry is a config file whose settings override the
+// shared ones, and runs as its own child process so its watchers, wallet
+// and reporting stay isolated. Children that exit are restarted.
+import path from 'path';
+import readline from 'readline';
+import { fork } from 'child_process';
+import { profileEnv } from './config.mjs';
+import { supervise } from './supervisor.mjs';
+import { runSystemdNotifier } from './systemd.mjs';
+
+const children = {};
+
+// Resolves when the child exits cleanly, rejects otherwise
+function runProfile(name, env, prefixLogs) {
+  return new Promise((resolve, reject) => {
+    const child = fork(process.argv[1], ['run'], {
+      env: env,
+      stdio: prefixLogs ? ['ignore', 'pipe', 'pipe', 'ipc'] : 'inherit'
+    });
+    children[name] = child;
+    if (prefixLogs) {
+      [[child.stdout, process.stdout], [child.stderr, process.stderr]].forEach(([from, to]) => {
+        readline.createInterface({ input: from }).on('line', (line) => to.write(`[${name}] ${line}\n`));
+      });
+    }
+    child.on('error', reject);
+    child.on('exit', (code, signal) => {
+      if (code === 0) {
+        resolve();
+      } else {
+        reject(new Error(`profile ${name} exited with ${signal || `code ${code}`}`));
+      }
+    });
+  });
+}
+
+// Never returns. Throws if a profile's settings don't check out.
+export async function runProfiles(files) {
+  const profiles = files.map((file) => {
+    const env = profileEnv(file);
+    // The parent talks to systemd for everyone
+    delete env.NOTIFY_SOCKET;
+    delete env.WATCHDOG_USEC;
+    return { name: path.basename(file, '.toml'), env: env };
+  });
+  process.on('exit', () => Object.values(children).forEach((child) => child.kill()));
+  // Children reload .env on SIGHUP. Profile files are only read at startup.
+  process.on('SIGHUP', () => Object.values(children).forEach((child) => child.kill('SIGHUP')));
+
+  // JSON log lines are left alone, they carry pinger_name
+  const prefixLogs = (process.env.LOG_FORMAT || 'text') === 'text';
+  runSystemdNotifier(
+    () => true,
+    () => Object.values(children).every((child) => child.exitCode === null)
+  );
+  await Promise.all(profiles.map(({ name, env }) =>
+    supervise(`profile:${name}`, () => runProfile(name, env, prefixLogs))
+  ));
+}
@@ -0,0 +1,225 @@
+// Outbound proxy, for pingers that can only reach the internet through one.
+// PROXY_URL, or else the usual HTTPS_PROXY / HTTP_PROXY, is an http://,
+// https:// or socks5:// URL. Hosts in NO_PROXY (comma separated, a leading
+// dot or * matching subdomains, * alone matching everything) and localhost
+// go direct.
+//
+// Every connection is tunnelled, with CONNECT through an HTTP proxy or a
+// SOCKS5 connect, by agents built on node's http and https modules. Node's
+// fetch can't use those agents, so it is replaced with agentFetch for
+// proxied hosts, and web3.js connections get the agents via
+// proxyFetchMiddleware. Websocket subscriptions don't go through the proxy.
+import http from 'http';
+import https from 'https';
+import net from 'net';
+import tls from 'tls';
+import { Readable } from 'stream';
+import { timestamp } from './misc.mjs';
+
+const PROXY_PROTOCOLS = ['http:', 'https:', 'socks:', 'socks5:', 'socks5h:'];
+
+let proxy;
+
+// The proxy URL from env, or undefined for none
+export function proxyUrl(env = process.env) {
+  return env.PROXY_URL || env.HTTPS_PROXY || env.https_proxy || env.HTTP_PROXY || env.http_proxy || undefined;
+}
+
+// Whether url skips the proxy because of NO_PROXY
+export function bypassesProxy(url, noProxy) {
+  let hostname;
+  try {
+    hostname = new URL(url).hostname.replace(/^\[|\]$/g, '');
+  } catch (e) {
+    return false;
+  }
+  if (['localhost', '127.0.0.1', '::1'].includes(hostname)) return true;
+  return noProxy
+    .split(',')
+    .map((entry) => entry.trim().replace(/:\d+$/, '').toLowerCase())
+    .filter((entry) => entry)
+    .some((entry) => {
+      if (entry === '*') return true;
+      const domain = entry.replace(/^\*?\./, '');
+      return hostname === domain || hostname.endsWith(`.${domain}`);
+    });
+}
+
+// Resolves with the next `length` bytes the socket sends, keeping any extra
+function readBytes(socket, state, length) {
+  return new Promise((resolve, reject) => {
+    const check = () => {
+      if (state.buffer.length < length) return false;
+      const bytes = state.buffer.subarray(0, length);
+      state.buffer = state.buffer.subarray(length);
+      socket.off('data', onData);
+      socket.off('error', reject);
+      socket.off('close', onClose);
+      resolve(bytes);
+      return true;
+    };
+    const onData = (data) => {
+      state.buffer = Buffer.concat([state.buffer, data]);
+      check();
+    };
+    const onClose = () => reject(new Error('SOCKS proxy closed the connection'));
+    if (check()) return;
+    socket.on('data', onData);
+    socket.once('error', reject);
+    socket.once('close', onClose);
+  });
+}
+
+// A socket to host:port through the SOCKS5 proxy at proxyUrl
+export async function socksTunnel(proxyUrl, host, port) {
+  const { hostname, port: proxyPort, username, password } = proxyUrl;
+  const socket = net.connect(Number(proxyPort || 1080), hostname);
+  await new Promise((resolve, reject) => {
+    socket.once('connect', resolve);
+    socket.once('error', reject);
+  });
+  const state = { buffer: Buffer.alloc(0) };
+  try {
+    socket.write(Buffer.from(username ? [5, 1, 2] : [5, 1, 0]));
+    const [, method] = await readBytes(socket, state, 2);
+    if (method === 2) {
+      const user = Buffer.from(decodeURIComponent(username));
+      const pass = Buffer.from(decodeURIComponent(password));
+      socket.write(Buffer.concat([Buffer.from([1, user.length]), user, Buffer.from([pass.length]), pass]));
+      const [, status] = await readBytes(socket, state, 2);
+      if (status !== 0) throw new Error('SOCKS proxy rejected the username or password');
+    } else if (method !== 0) {
+      throw new Error('SOCKS proxy offered no usable authentication method');
+    }
+    const hostBytes = Buffer.from(host);
+    socket.write(Buffer.concat([
+      Buffer.from([5, 1, 0, 3, hostBytes.length]),
+      hostBytes,
+      Buffer.from([port >> 8, port & 0xff])
+    ]));
+    const [, reply, , addressType] = await readBytes(socket, state, 4);
+    if (reply !== 0) throw new Error(`SOCKS proxy could not connect to ${host}:${port} (reply ${reply})`);
+    // Skip the bound address and port
+    const addressLength = addressType === 1 ? 4 : addressType === 4 ? 16 : (await readBytes(socket, state, 1))[0];
+    await readBytes(socket, state, addressLength + 2);
+  } catch (e) {
+    socket.destroy();
+    throw e;
+  }
+  return socket;
+}
+
+// A socket to host:port through a CONNECT on the HTTP proxy at proxyUrl
+export function connectTunnel(proxyUrl, host, port) {
+  const headers = { host: `${host}:${port}` };
+  if (proxyUrl.username) {
+    const credentials = `${decodeURIComponent(proxyUrl.username)}:${decodeURIComponent(proxyUrl.password)}`;
+    headers['proxy-authorization'] = `Basic ${Buffer.from(credentials).toString('base64')}`;
+  }
+  return new Promise((resolve, reject) => {
+    const request = (proxyUrl.protocol === 'https:' ? https : http).request({
+      host: proxyUrl.hostname,
+      port: proxyUrl.port || (proxyUrl.protocol === 'https:' ? 443 : 80),
+      method: 'CONNECT',
+      path: `${host}:${port}`,
+      headers: headers
+    });
+    request.once('connect', (response, socket) => {
+      if (response.statusCode === 200) return resolve(socket);
+      socket.destroy();
+      reject(new Error(`Proxy CONNECT to ${host}:${port} returned ${response.statusCode}`));
+    });
+    request.once('error', reject);
+    request.end();
+  });
+}
+
+// http and https agents whose connections are tunnelled through proxyUrl
+function tunnelAgents(proxyUrl) {
+  const tunnel = proxyUrl.protocol.startsWith('socks') ? socksTunnel : connectTunnel;
+  const createConnection = (secure) => function(options, callback) {
+    tunnel(proxyUrl, options.host, Number(options.port)).then(
+      (socket) => {
+        if (!secure) return callback(null, socket);
+        const servername = options.servername || (net.isIP(options.host) ? undefined : options.host);
+        const tlsSocket = tls.connect({ ...options, socket: socket, servername: servername });
+        tlsSocket.once('secureConnect', () => callback(null, tlsSocket));
+        tlsSocket.once('error', callback);
+      },
+      callback
+    );
+  };
+  const httpAgent = new http.Agent({ keepAlive: true });
+  httpAgent.createConnection = createConnection(false);
+  const httpsAgent = new https.Agent({ keepAlive: true });
+  httpsAgent.createConnection = createConnection(true);
+  return { http: httpAgent, https: httpsAgent };
+}
+
+// The subset of fetch this client uses (method, headers, body and signal),
+// over node's http modules so the request can go through agents
+function agentFetch(input, init = {}, agents) {
+  const url = new URL(typeof input === 'string' || input instanceof URL ? String(input) : input.url);
+  const secure = url.protocol === 'https:';
+  return new Promise((resolve, reject) => {
+    const request = (secure ? https : http).request(url, {
+      method: init.method || 'GET',
+      headers: Object.fromEntries(new Headers(init.headers).entries()),
+      agent: secure ? agents.https : agents.http,
+      signal: init.signal
+    });
+    request.once('response', (response) => {
+      const headers = new Headers();
+      Object.entries(response.headers).forEach(([name, value]) => {
+        [].concat(value).forEach((v) => headers.append(name, v));
+      });
+      const empty = [204, 205, 304].includes(response.statusCode) || request.method === 'HEAD';
+      if (empty) response.resume();
+      resolve(new Response(empty ? null : Readable.toWeb(response), {
+        status: response.statusCode,
+        statusText: response.statusMessage,
+        headers: headers
+      }));
+    });
+    request.once('error', reject);
+    request.end(init.body);
+  });
+}
+
+// Send fetch, and the connections from rpcConnection, through the proxy in
+// env. Does nothing without one. Throws if the URL isn't one we can use.
+export function setupProxy(env = process.env) {
+  const url = proxyUrl(env);
+  if (!url) return;
+  let parsed;
+  try {
+    parsed = new URL(url);
+  } catch (e) {
+    throw new Error('The proxy URL is not a valid URL');
+  }
+  if (!PROXY_PROTOCOLS.includes(parsed.protocol)) {
+    throw new Error('The proxy URL must be http://, https:// or socks5://');
+  }
+  const noProxy = env.NO_PROXY || env.no_proxy || '';
+  const agents = tunnelAgents(parsed);
+  const direct = globalThis.fetch;
+  globalThis.fetch = (input, init) => {
+    const target = typeof input === 'string' || input instanceof URL ? String(input) : input.url;
+    return bypassesProxy(target, noProxy) ? direct(input, init) : agentFetch(input, init, agents);
+  };
+  proxy = { noProxy, agents };
+  const kind = parsed.protocol.startsWith('socks') ? 'SOCKS' : 'HTTP';
+  console.log(`${timestamp()} Sending HTTP requests through ${kind} proxy ${parsed.host}`);
+}
+
+// A web3.js fetchMiddleware that sends RPC calls through the proxy, or
+// undefined without one
+export function proxyFetchMiddleware() {
+  if (!proxy) return undefined;
+  return (info, init, fetch) => {
+    const target = typeof info === 'string' ? info : info.url;
+    if (bypassesProxy(target, proxy.noProxy)) return fetch(info, init);
+    const agent = new URL(target).protocol === 'https:' ? proxy.agents.https : proxy.agents.http;
+    return fetch(info, { ...init, agent });
+  };
+}
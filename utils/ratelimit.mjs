@@ -0,0 +1,68 @@
+// Back off from RPC rate limits. When any polling task gets a 429, every
+// polling task waits until the Retry-After time (or, without one, a delay
+// that doubles with each 429 in a row) before its next call, so the
+// watchers don't keep each other over the limit.
+import { timestamp } from './misc.mjs';
+
+// Without Retry-After: 1s, doubling up to a minute
+const BASE_DELAY_MS = 1000;
+const MAX_DELAY_MS = 60000;
+// 429s further apart than this start the doubling over
+const STREAK_RESET_MS = 60000;
+
+// Shared state. limitedUntil is when polling may resume, hits counts 429s
+// per task.
+export const rateLimit = {
+  limitedUntil: 0,
+  streak: 0,
+  lastHitAt: 0,
+  hits: {}
+};
+
+// Thrown by our own fetches on a 429
+export class RateLimitedError extends Error {
+  constructor(what, retryAfterMs) {
+    super(`${what} was rate limited (429)`);
+    this.name = 'RateLimitedError';
+    this.retryAfterMs = retryAfterMs;
+  }
+}
+
+// Retry-After is either seconds or an HTTP date
+export function parseRetryAfter(header) {
+  if (!header) return undefined;
+  const seconds = Number(header);
+  if (!Number.isNaN(seconds)) return seconds * 1000;
+  const date = Date.parse(header);
+  return Number.isNaN(date) ? undefined : Math.max(0, date - Date.now());
+}
+
+// Throws RateLimitedError if response is a 429
+export function checkRateLimit(response, what) {
+  if (response.status === 429) {
+    throw new RateLimitedError(what, parseRetryAfter(response.headers.get('retry-after')));
+  }
+}
+
+// web3.js gives up on 429s with an error mentioning the status
+export const isRateLimited = (e) =>
+  e instanceof RateLimitedError || /\b429\b|Too Many Requests/.test(e.message || '');
+
+// Call from a polling task's error handler. Returns whether e was a 429.
+export function noteRateLimit(task, e) {
+  if (!isRateLimited(e)) return false;
+  const now = Date.now();
+  rateLimit.streak = now - rateLimit.lastHitAt > STREAK_RESET_MS ? 1 : rateLimit.streak + 1;
+  rateLimit.lastHitAt = now;
+  rateLimit.hits[task] = (rateLimit.hits[task] || 0) + 1;
+  const delayMs = e.retryAfterMs ?? Math.min(MAX_DELAY_MS, BASE_DELAY_MS * 2 ** (rateLimit.streak - 1));
+  if (now + delayMs > rateLimit.limitedUntil) {
+    rateLimit.limitedUntil = now + delayMs;
+    console.log(`${timestamp()} WARNING: ${task} was rate limited, pausing RPC polling for ${delayMs}ms`);
+  }
+  return true;
+}
+
+// A polling task's next delay, stretched to the end of any rate limit
+export const rateLimitedDelay = (delayMs) =>
+  Math.max(Number(delayMs), rateLimit.limitedUntil - Date.now());
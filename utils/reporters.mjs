@@ -0,0 +1,188 @@
+// Where each ping goes once it is done. A reporter is { name, report(ping) }
+// where ping is { result, record, span }: result is the payload sent to
+// validators.app, record adds what only we know about the ping (see the
+// sinks for its fields) and span is the ping's trace span. report() must not
+// throw or block the loop; sinks that do I/O do it in the background.
+//
+// REPORTERS lists the reporters to use. Without it every sink whose
+// settings are set is used, plus validators_app and prometheus.
+import { postPingResult } from './webhook.mjs';
+import { appendPingRecord } from './pinglog.mjs';
+import { appendPingCsv } from './csvexport.mjs';
+import { insertPing } from './sqlite.mjs';
+import { addClickhouseRow } from './clickhouse.mjs';
+import { pingResultLine, writeToInflux } from './influx.mjs';
+import { captureError } from './sentry.mjs';
+import { notify } from './notifier.mjs';
+import { enqueueVaPayload, isRetryableStatus } from './vaqueue.mjs';
+import { addToVaBatch } from './vabatch.mjs';
+import {
+  confirmationLatencyMs,
+  commitmentLatencyMs,
+  latencyPhaseMs,
+  processedToConfirmedMs,
+  computeUnitsConsumed,
+  vaSubmissionErrorsTotal
+} from './pingmetrics.mjs';
+import { timestamp } from './misc.mjs';
+
+const POST_TIMEOUT_MS = 10000;
+
+// POST each result to validators.app, or add it to the batch when
+// batchIntervalMs is set. Failed POSTs that may succeed later are queued.
+// Goes through fetch, so it uses the proxy if there is one.
+export function vaReporter({ url, apiKey, batchIntervalMs, batchMaxSize }) {
+  const fail = (error, signature, span) => {
+    vaSubmissionErrorsTotal.inc();
+    console.log(`${timestamp()} ERROR: ${error.message}`);
+    captureError(error, 'va_submission', { signature: signature });
+    notify('va_submission', error.message);
+    span.end(error);
+  };
+
+  return {
+    name: 'validators_app',
+    report: ({ result, span }) => {
+      const payload = JSON.stringify(result);
+      if (batchIntervalMs) {
+        addToVaBatch(payload, batchMaxSize);
+        return;
+      }
+      const reportSpan = span.child('report');
+      fetch(url, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json', 'Token': apiKey },
+        body: payload,
+        signal: AbortSignal.timeout(POST_TIMEOUT_MS)
+      }).then(
+        (response) => {
+          if (response.status >= 400) {
+            if (isRetryableStatus(response.status)) enqueueVaPayload(payload);
+            fail(new Error(`validators.app returned ${response.status}`), result.signature, reportSpan);
+          } else {
+            reportSpan.end();
+          }
+        },
+        () => {
+          enqueueVaPayload(payload);
+          fail(new Error('Unable to reach validators.app'), result.signature, reportSpan);
+        }
+      );
+    }
+  };
+}
+
+// The per-ping latency and compute histograms served on /metrics
+export function prometheusReporter() {
+  return {
+    name: 'prometheus',
+    report: ({ record }) => {
+      if (record.success) {
+        confirmationLatencyMs.observe({ commitment: record.commitment_level }, record.time);
+      }
+      if (record.inclusion_ms !== undefined) {
+        latencyPhaseMs.observe({ phase: 'send' }, record.send_ms);
+        latencyPhaseMs.observe({ phase: 'propagation' }, Math.max(0, record.inclusion_ms - record.send_ms));
+        latencyPhaseMs.observe({ phase: 'inclusion' }, record.time - Math.max(record.inclusion_ms, record.send_ms));
+      }
+      ['processed', 'confirmed', 'finalized'].forEach((commitment) => {
+        commitmentLatencyMs.observe({ commitment }, record[`${commitment}_time`]);
+      });
+      processedToConfirmedMs.observe({}, record.processed_to_confirmed_ms);
+      computeUnitsConsumed.observe({}, record.compute_units_consumed);
+    }
+  };
+}
+
+// Each record as one line of JSON on stdout
+export function stdoutReporter() {
+  return {
+    name: 'stdout',
+    report: ({ record }) => process.stdout.write(`${JSON.stringify(record)}\n`)
+  };
+}
+
+export function jsonlReporter(file) {
+  return { name: 'jsonl', report: ({ record }) => appendPingRecord(file, record) };
+}
+
+export function csvReporter(dir) {
+  return { name: 'csv', report: ({ record }) => appendPingCsv(dir, record) };
+}
+
+export function sqliteReporter(db) {
+  return { name: 'sqlite', report: ({ record }) => insertPing(db, record) };
+}
+
+// Rows are buffered for runClickhouseSink
+export function clickhouseReporter() {
+  return { name: 'clickhouse', report: ({ record }) => addClickhouseRow(record) };
+}
+
+export function influxReporter(writeUrl, token, { pingerName, region }) {
+  return {
+    name: 'influxdb',
+    report: ({ result, record }) => {
+      writeToInflux(
+        writeUrl,
+        token,
+        pingResultLine(result, { pingerName, region, timeMs: Date.parse(record.sent_at) })
+      );
+    }
+  };
+}
+
+export function webhookReporter(url, headers, template) {
+  return { name: 'webhook', report: ({ record }) => postPingResult(url, headers, template, record) };
+}
+
+// Each reporter by name, with the option it can't do without, if any
+const REPORTERS = {
+  validators_app: { create: (o) => vaReporter(o.va) },
+  prometheus: { create: () => prometheusReporter() },
+  stdout: { create: () => stdoutReporter() },
+  jsonl: { needs: 'PING_LOG_FILE', create: (o) => jsonlReporter(o.pingLogFile) },
+  csv: { needs: 'PING_CSV_DIR', create: (o) => csvReporter(o.pingCsvDir) },
+  sqlite: { needs: 'PING_SQLITE_DB', create: (o) => sqliteReporter(o.pingSqliteDb) },
+  clickhouse: { needs: 'CLICKHOUSE_URL', create: () => clickhouseReporter() },
+  influxdb: {
+    needs: 'INFLUXDB_WRITE_URL',
+    create: (o) => influxReporter(o.influx.writeUrl, o.influx.token, o)
+  },
+  webhook: {
+    needs: 'PING_WEBHOOK_URL',
+    create: (o) => webhookReporter(o.webhook.url, o.webhook.headers, o.webhook.template)
+  }
+};
+
+export const REPORTER_NAMES = Object.keys(REPORTERS);
+
+// The reporters `names` selects, or the default set when names is empty.
+// env holds the settings the reporters need and options their values:
+// va, pingLogFile, pingCsvDir, pingSqliteDb, influx, webhook, pingerName
+// and region. Throws for unknown names and missing settings.
+export function createReporters(names, env, options) {
+  const selected = names.length
+    ? names
+    : REPORTER_NAMES.filter((name) => !REPORTERS[name].needs ? name !== 'stdout' : env[REPORTERS[name].needs]);
+  return selected.map((name) => {
+    const reporter = REPORTERS[name];
+    if (!reporter) throw new Error(`REPORTERS must be a list of ${REPORTER_NAMES.join(', ')}`);
+    if (reporter.needs && !env[reporter.needs]) {
+      throw new Error(`REPORTERS includes ${name}, which needs ${reporter.needs}`);
+    }
+    return reporter.create(options);
+  });
+}
+
+// Hand a finished ping to every reporter. A reporter that throws is logged
+// and doesn't stop the others.
+export function reportPing(reporters, ping) {
+  reporters.forEach((reporter) => {
+    try {
+      reporter.report(ping);
+    } catch (e) {
+      console.log(`${timestamp()} ERROR: Reporter ${reporter.name} failed: ${e.message}`);
+    }
+  });
+}
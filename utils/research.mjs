@@ -0,0 +1,58 @@
+// export-research: write stored pings in a fixed format for priority fee
+// recommendation research. The format is described by
+// research-schema-v1.json. Fields are only ever added within a version;
+// renaming, removing or changing the meaning of one bumps RESEARCH_SCHEMA_VERSION
+// and gets a new schema file.
+import fs from 'fs';
+import path from 'path';
+import { loadPingRecords } from './pingstore.mjs';
+
+export const RESEARCH_SCHEMA_VERSION = 1;
+
+const value = (v) => (v === undefined ? null : v);
+
+// A stored ping record as a research row
+export function researchRow(record) {
+  return {
+    schema_version: RESEARCH_SCHEMA_VERSION,
+    tx_signature: value(record.tx_signature),
+    sent_at: record.sent_at,
+    pinger_name: value(record.pinger_name),
+    region: value(record.region),
+    commitment_level: value(record.commitment_level),
+    slot_sent: value(record.slot_sent),
+    leader_sent: value(record.leader_sent),
+    slots_to_next_leader: value(record.slots_to_next_leader),
+    priority_fee_micro_lamports: value(record.priority_fee_micro_lamports),
+    compute_unit_limit: value(record.compute_unit_limit),
+    compute_units_consumed: value(record.compute_units_consumed),
+    fee_lamports: value(record.fee_lamports),
+    success: record.success,
+    reason: value(record.reason),
+    slot_landed: value(record.slot_landed),
+    leader_landed: value(record.leader_landed),
+    tx_index: value(record.tx_index),
+    confirmation_ms: value(record.time),
+    rpc_rtt_ms: value(record.rpc_rtt_ms),
+    send_ms: value(record.send_ms),
+    inclusion_ms: value(record.inclusion_ms)
+  };
+}
+
+// Writes ping-thing-research-v<version>-<time>.jsonl to RESEARCH_EXPORT_DIR
+// (default the current directory) with every ping sent since
+// RESEARCH_EXPORT_SINCE (an ISO time, default all). Resolves to the exit
+// code.
+export async function exportResearchCommand(env) {
+  const sinceMs = env.RESEARCH_EXPORT_SINCE ? Date.parse(env.RESEARCH_EXPORT_SINCE) : 0;
+  if (Number.isNaN(sinceMs)) throw new Error('RESEARCH_EXPORT_SINCE must be an ISO time');
+  const records = await loadPingRecords(env, sinceMs);
+  const stamp = new Date().toISOString().replace(/[-:]/g, '').slice(0, 15);
+  const file = path.join(
+    env.RESEARCH_EXPORT_DIR || '.',
+    `ping-thing-research-v${RESEARCH_SCHEMA_VERSION}-${stamp}.jsonl`
+  );
+  fs.writeFileSync(file, records.map((r) => JSON.stringify(researchRow(r)) + '\n').join(''));
+  console.log(`Wrote ${records.length} pings to ${file}`);
+  return 0;
+}
@@ -0,0 +1,47 @@
+// Re-broadcast a signed TX while we wait for it to confirm. The delay
+// between resends is either fixed, exponential, or exponential with full
+// jitter, and resending stops once the blockhash has expired.
+import { sleep, timestamp } from './misc.mjs';
+import { globalBlockHeight } from './blockheight.mjs';
+
+export const RESEND_BACKOFF_MODES = ['fixed', 'exponential', 'jittered'];
+
+function resendDelayMs(intervalMs, backoff, attempt) {
+  if (backoff === 'fixed') return intervalMs;
+
+  const exponential = intervalMs * 2 ** attempt;
+  return backoff === 'jittered' ? Math.random() * exponential : exponential;
+}
+
+// Starts resending in the background and returns a handle. Call stop() once
+// the TX has confirmed or timed out; `resends` holds the number of resends
+// performed. `send` re-broadcasts the TX and may throw.
+export function startResending(
+  send,
+  lastValidBlockHeight,
+  { intervalMs, maxResends, backoff }
+) {
+  const handle = {
+    resends: 0,
+    stopped: false,
+    stop() { this.stopped = true; }
+  };
+
+  (async () => {
+    while (handle.resends < maxResends) {
+      await sleep(resendDelayMs(Number(intervalMs), backoff, handle.resends));
+      if (handle.stopped) break;
+      // No point resending a TX whose blockhash has expired
+      if (globalBlockHeight.blockHeight > lastValidBlockHeight) break;
+
+      handle.resends++;
+      try {
+        await send();
+      } catch (e) {
+        console.log(`${timestamp()} ERROR: Resend failed: ${e.message}`);
+      }
+    }
+  })();
+
+  return handle;
+}
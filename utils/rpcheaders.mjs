@@ -0,0 +1,58 @@
+// Extra HTTP headers for our RPC endpoints, for providers that authenticate
+// with e.g. Authorization or x-api-key rather than a token in the URL.
+// RPC_HEADERS is a JSON object. It only goes to RPC_ENDPOINT and
+// RPC_ENDPOINTS, so a separate PRIORITY_FEE_API_URL or other host never sees
+// the credentials. Our web3.js version can't set headers on its websocket,
+// so slot and signature subscriptions go without them. Connections are
+// built at startup, so a new RPC_HEADERS needs a restart; SIGHUP doesn't
+// apply it.
+import web3 from '@solana/web3.js';
+import { proxyFetchMiddleware } from './proxy.mjs';
+
+let parsed;
+
+function parseRpcHeaders(value) {
+  if (parsed && parsed.value === value) return parsed.headers;
+  let headers;
+  try {
+    headers = JSON.parse(value);
+  } catch (e) {
+    headers = undefined;
+  }
+  if (!headers || typeof headers !== 'object' || Array.isArray(headers)) {
+    throw new Error('RPC_HEADERS must be a JSON object, e.g. {"Authorization": "Bearer ..."}');
+  }
+  parsed = { value, headers };
+  return headers;
+}
+
+const origin = (url) => {
+  try {
+    return new URL(url).origin;
+  } catch (e) {
+    return undefined;
+  }
+};
+
+// The headers to send to url: RPC_HEADERS if url is one of our RPC
+// endpoints, otherwise none
+export function rpcHeaders(url, env = process.env) {
+  if (!env.RPC_HEADERS) return {};
+  const endpoints = [env.RPC_ENDPOINT, ...(env.RPC_ENDPOINTS || '').split(',')]
+    .map((e) => (e || '').trim())
+    .filter((e) => e);
+  const headers = parseRpcHeaders(env.RPC_HEADERS);
+  return endpoints.some((e) => origin(e) === origin(url)) ? headers : {};
+}
+
+// A web3.js Connection to url with its headers. config is a commitment level
+// or a ConnectionConfig, as for web3.Connection.
+// Also sends through the proxy, if there is one, see setupProxy.
+export function rpcConnection(url, config, env = process.env) {
+  const headers = rpcHeaders(url, env);
+  const fetchMiddleware = proxyFetchMiddleware();
+  if (!Object.keys(headers).length && !fetchMiddleware) return new web3.Connection(url, config);
+  const base = typeof config === 'string' ? { commitment: config } : { ...config };
+  if (fetchMiddleware) base.fetchMiddleware = fetchMiddleware;
+  return new web3.Connection(url, { ...base, httpHeaders: { ...base.httpHeaders, ...headers } });
+}
@@ -0,0 +1,37 @@
+// Baseline round-trip time to the RPC endpoint, so confirmation latency can
+// be split into time on the network and time in the cluster. getVersion does
+// no cluster work, so its round trip is close to pure network latency.
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+import { rateLimitedDelay, noteRateLimit } from './ratelimit.mjs';
+
+// Samples per measurement. The fastest is the baseline, as slower ones
+// include queueing on either end.
+const SAMPLES = 3;
+
+// Shared state. ms is the latest baseline RTT.
+export const globalRtt = {
+  ms: undefined,
+  updatedAt: undefined
+};
+
+export async function watchRtt(connection, intervalMs) {
+  let failures = 0;
+  while (true) {
+    try {
+      const samples = [];
+      for (let i = 0; i < SAMPLES; i++) {
+        const start = Date.now();
+        await connection.getVersion();
+        samples.push(Date.now() - start);
+      }
+      globalRtt.ms = Math.min(...samples);
+      globalRtt.updatedAt = Date.now();
+      failures = 0;
+    } catch (e) {
+      failures++;
+      noteRateLimit('rtt', e);
+      console.log(`${timestamp()} ERROR: Unable to measure RPC round-trip time (${failures} in a row): ${e.message}`);
+    }
+    await sleep(rateLimitedDelay(failures ? backoffMs(failures, intervalMs) : intervalMs));
+  }
+}
@@ -0,0 +1,59 @@
+// Estimate how long each wallet lasts at the current spend rate: its balance
+// divided by the lamports it spent per hour over the last RUNWAY_WINDOW_MS.
+// Falling below the alert threshold is logged and sent to the notifier.
+import { timestamp } from './misc.mjs';
+import { notify } from './notifier.mjs';
+import { globalBalance } from './balance.mjs';
+
+// Need at least this much history before estimating a rate
+const MIN_SPAN_MS = 60000;
+// Repeat the warning at most this often
+const RUNWAY_WARNING_INTERVAL_MS = 3600000;
+
+// wallet label => [{ at, lamports }]
+const spends = {};
+const lastWarningAt = {};
+let runwayConfig = { windowMs: 3600000, alertHours: 0 };
+
+// Shared state, by wallet label
+export const walletRunway = {
+  hours: {},
+  lamportsPerPing: {}
+};
+
+// alertHours of 0 disables the alert but the gauges are still kept
+export function configureRunway({ windowMs, alertHours }) {
+  runwayConfig = { windowMs, alertHours };
+}
+
+function update(wallet) {
+  const history = spends[wallet];
+  const spent = history.reduce((sum, s) => sum + s.lamports, 0);
+  walletRunway.lamportsPerPing[wallet] = spent / history.length;
+
+  const spanMs = Date.now() - history[0].at;
+  const balance = globalBalance.wallets[wallet];
+  if (spanMs < MIN_SPAN_MS || balance === undefined || spent === 0) return;
+  const hours = balance / (spent / (spanMs / 3600000));
+  walletRunway.hours[wallet] = hours;
+
+  const { alertHours } = runwayConfig;
+  if (!alertHours || hours >= alertHours) {
+    lastWarningAt[wallet] = 0;
+  } else if (Date.now() - (lastWarningAt[wallet] || 0) > RUNWAY_WARNING_INTERVAL_MS) {
+    lastWarningAt[wallet] = Date.now();
+    const message = `Wallet ${wallet} has about ${hours.toFixed(1)} hours of pings left (${balance} lamports, ${Math.round(walletRunway.lamportsPerPing[wallet])} lamports per ping)`;
+    console.log(`${timestamp()} WARNING: ${message}`);
+    notify(`runway_${wallet}`, message);
+  }
+}
+
+// Record the fee a ping paid. Pings that never landed cost nothing but
+// still count towards lamports per ping.
+export function recordSpend(wallet, lamports) {
+  const now = Date.now();
+  const history = (spends[wallet] = spends[wallet] || []);
+  history.push({ at: now, lamports: lamports });
+  while (history.length > 1 && now - history[0].at > runwayConfig.windowMs) history.shift();
+  update(wallet);
+}
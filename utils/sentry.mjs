@@ -0,0 +1,76 @@
+// Optional error reporting to Sentry. Events are sent to the envelope
+// endpoint derived from SENTRY_DSN with plain fetch, so no SDK is needed.
+// Every event is tagged with the pinger name, region and RPC endpoint.
+import crypto from 'crypto';
+import { timestamp } from './misc.mjs';
+
+const REPORT_TIMEOUT_MS = 5000;
+
+let sentry;
+
+// dsn looks like https://<public key>@<host>/<project id>
+export function configureSentry(dsn, tags) {
+  const url = new URL(dsn);
+  const projectId = url.pathname.replace(/^\/|\/$/g, '');
+  sentry = {
+    dsn: dsn,
+    envelopeUrl: `${url.protocol}//${url.host}/api/${projectId}/envelope/`,
+    auth: `Sentry sentry_version=7, sentry_client=ping-thing-client/1.0, sentry_key=${url.username}`,
+    tags: Object.fromEntries(Object.entries(tags).filter(([, v]) => v !== undefined))
+  };
+}
+
+function exceptionFrom(error) {
+  return {
+    type: error && error.name ? error.name : 'Error',
+    value: error && error.message ? error.message : String(error),
+    stacktrace: error && error.stack
+      ? {
+          frames: error.stack
+            .split('\n')
+            .slice(1)
+            .map((line) => ({ function: line.trim() }))
+            .reverse()
+        }
+      : undefined
+  };
+}
+
+// Report an error. `source` says where it came from (e.g. watcher:slot,
+// va_submission, crash) and `extra` carries context such as the signature.
+// Resolves once Sentry has answered or the request timed out, and never
+// rejects.
+export async function captureError(error, source, extra = {}, level = 'error') {
+  if (!sentry) return;
+
+  const eventId = crypto.randomUUID().replace(/-/g, '');
+  const event = {
+    event_id: eventId,
+    timestamp: Date.now() / 1000,
+    platform: 'node',
+    level: level,
+    tags: { ...sentry.tags, source: source },
+    extra: extra,
+    exception: { values: [exceptionFrom(error)] }
+  };
+  const envelope = [
+    JSON.stringify({ event_id: eventId, dsn: sentry.dsn, sent_at: new Date().toISOString() }),
+    JSON.stringify({ type: 'event' }),
+    JSON.stringify(event)
+  ].join('\n');
+
+  try {
+    const response = await fetch(sentry.envelopeUrl, {
+      method: 'POST',
+      headers: {
+        'Content-Type': 'application/x-sentry-envelope',
+        'X-Sentry-Auth': sentry.auth
+      },
+      body: envelope,
+      signal: AbortSignal.timeout(REPORT_TIMEOUT_MS)
+    });
+    if (!response.ok) throw new Error(`HTTP ${response.status}`);
+  } catch (e) {
+    console.log(`${timestamp()} ERROR: Unable to report to Sentry: ${e.message}`);
+  }
+}
@@ -0,0 +1,46 @@
+// A tiny HTTP server for /metrics, /healthz and other status endpoints.
+// Handlers are registered per path and method and return
+// { status, contentType, body }.
+import http from 'http';
+import { timestamp } from './misc.mjs';
+
+const routes = new Map();
+
+export function addRoute(path, handler, method = 'GET') {
+  routes.set(`${method} ${path}`, handler);
+}
+
+export function startServer(port, bindAddress) {
+  const server = http.createServer(async (req, res) => {
+    const path = new URL(req.url, 'http://localhost').pathname;
+    const handler = routes.get(`${req.method} ${path}`);
+    if (!handler) {
+      res.writeHead(404, { 'Content-Type': 'text/plain' });
+      res.end('Not found\n');
+      return;
+    }
+
+    try {
+      const { status, contentType, body } = await handler(req);
+      res.writeHead(status, { 'Content-Type': contentType });
+      res.end(body);
+    } catch (e) {
+      console.log(`${timestamp()} ERROR: ${path} handler failed: ${e.message}`);
+      res.writeHead(500, { 'Content-Type': 'text/plain' });
+      res.end('Internal error\n');
+    }
+  });
+
+  server.listen(port, bindAddress, () => {
+    console.log(`${timestamp()} Status server listening on ${bindAddress}:${port}`);
+  });
+  return server;
+}
+
+export function jsonResponse(status, value) {
+  return {
+    status: status,
+    contentType: 'application/json',
+    body: JSON.stringify(value) + '\n'
+  };
+}
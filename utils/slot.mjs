@@ -0,0 +1,79 @@
+// Follow the current slot over the websocket so we know which slot a TX was
+// sent in without an extra RPC call.
+import { sleep, timestamp } from './misc.mjs';
+import { rateLimitedDelay, noteRateLimit } from './ratelimit.mjs';
+
+// Shared state. updatedAt is a millisecond timestamp of the last update,
+// staleEvents counts how often the subscription went quiet and lagSlots is
+// how far the subscription was behind getSlot at the last check.
+export const globalSlot = {
+  slot: undefined,
+  updatedAt: undefined,
+  staleEvents: 0,
+  lagSlots: undefined
+};
+
+// When each recent slot was first seen, to time when a TX's slot started.
+// Slots come every ~400ms, so this covers the last ~7 minutes.
+const SLOT_TIMES_MAX = 1000;
+const slotTimes = new Map();
+
+function subscribe(connection) {
+  return connection.onSlotChange((slotInfo) => {
+    globalSlot.slot = slotInfo.slot;
+    globalSlot.updatedAt = Date.now();
+    if (!slotTimes.has(slotInfo.slot)) {
+      slotTimes.set(slotInfo.slot, globalSlot.updatedAt);
+      if (slotTimes.size > SLOT_TIMES_MAX) slotTimes.delete(slotTimes.keys().next().value);
+    }
+  });
+}
+
+// When slot was first seen by the subscription, or undefined if it wasn't
+export const slotSeenAt = (slot) => slotTimes.get(slot);
+
+// Slots arrive every ~400ms, so a subscription that has been quiet for
+// staleMs is assumed dead and is replaced with a new one.
+export async function watchSlot(connection, staleMs) {
+  let subscriptionId = subscribe(connection);
+  let subscribedAt = Date.now();
+  console.log(`${timestamp()} Subscribed to slot updates`);
+
+  while (true) {
+    await sleep(1000);
+
+    const lastUpdate = Math.max(globalSlot.updatedAt || 0, subscribedAt);
+    if (Date.now() - lastUpdate < staleMs) continue;
+
+    globalSlot.staleEvents++;
+    console.log(`${timestamp()} ERROR: No slot update for ${Date.now() - lastUpdate}ms. Resubscribing (stale events: ${globalSlot.staleEvents})`);
+    connection.removeSlotChangeListener(subscriptionId)
+      .catch((e) => console.log(`${timestamp()} ERROR: Unable to remove slot listener: ${e.message}`));
+    subscriptionId = subscribe(connection);
+    subscribedAt = Date.now();
+  }
+}
+
+// The cached slot if it was updated within maxAgeMs, otherwise undefined so
+// a stale slot is never reported as the slot a TX was sent in.
+export function freshSlot(maxAgeMs) {
+  if (globalSlot.updatedAt === undefined) return undefined;
+  return Date.now() - globalSlot.updatedAt <= maxAgeMs ? globalSlot.slot : undefined;
+}
+
+// Compare the subscription against a direct getSlot call every intervalMs to
+// measure how far behind the websocket feed is running.
+export async function watchSlotLag(connection, intervalMs) {
+  while (true) {
+    await sleep(rateLimitedDelay(intervalMs));
+    try {
+      const slot = await connection.getSlot('processed');
+      if (globalSlot.slot !== undefined) {
+        globalSlot.lagSlots = Math.max(0, slot - globalSlot.slot);
+      }
+    } catch (e) {
+      noteRateLimit('slot_lag', e);
+      console.log(`${timestamp()} ERROR: Unable to fetch slot: ${e.message}`);
+    }
+  }
+}
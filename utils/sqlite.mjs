@@ -0,0 +1,100 @@
+// Optional SQLite ping history. There's no SQLite binding in our Node
+// version, so this shells out to the sqlite3 CLI, one insert per ping.
+import { execFile, execFileSync } from 'child_process';
+import { timestamp } from './misc.mjs';
+
+// column => type, filled from the ping record of the same name. The late_*
+// columns are filled in later by updatePing.
+const COLUMNS = {
+  sent_at: 'TEXT NOT NULL',
+  pinger_name: 'TEXT',
+  region: 'TEXT',
+  tx_signature: 'TEXT',
+  transaction_type: 'TEXT',
+  success: 'INTEGER',
+  reason: 'TEXT',
+  error: 'TEXT',
+  error_category: 'TEXT',
+  commitment_level: 'TEXT',
+  time: 'INTEGER',
+  resends: 'INTEGER',
+  slot_sent: 'INTEGER',
+  slot_landed: 'INTEGER',
+  leader_sent: 'TEXT',
+  leader_landed: 'TEXT',
+  slots_to_next_leader: 'INTEGER',
+  priority_fee_micro_lamports: 'INTEGER',
+  priority_fee_percentile: 'REAL',
+  compute_unit_limit: 'INTEGER',
+  tx_size: 'INTEGER',
+  experiment: 'TEXT',
+  variant: 'TEXT',
+  compute_units_consumed: 'INTEGER',
+  fee_lamports: 'INTEGER',
+  tx_index: 'INTEGER',
+  rpc_rtt_ms: 'INTEGER',
+  send_ms: 'INTEGER',
+  inclusion_ms: 'INTEGER',
+  processed_time: 'INTEGER',
+  confirmed_time: 'INTEGER',
+  finalized_time: 'INTEGER',
+  // For timed out pings: landed or dropped, and when and where a late one
+  // landed
+  late_outcome: 'TEXT',
+  late_landing_ms: 'INTEGER',
+  late_slot_landed: 'INTEGER'
+};
+
+function sqlValue(value) {
+  if (value === undefined || value === null) return 'NULL';
+  if (typeof value === 'boolean') return value ? '1' : '0';
+  // NaN and Infinity aren't valid SQL literals
+  if (typeof value === 'number') return Number.isFinite(value) ? String(value) : 'NULL';
+  return `'${String(value).replace(/'/g, "''")}'`;
+}
+
+// Create the table and indices if needed, and add any columns missing from
+// a table made by an older version. Throws if sqlite3 is missing.
+export function openSqlite(db) {
+  const columns = Object.entries(COLUMNS).map(([name, type]) => `${name} ${type}`);
+  execFileSync('sqlite3', [db, `CREATE TABLE IF NOT EXISTS pings (${columns.join(', ')});`]);
+  const existing = JSON.parse(
+    execFileSync('sqlite3', ['-json', db, 'PRAGMA table_info(pings);']).toString() || '[]'
+  ).map(({ name }) => name);
+  const missing = Object.entries(COLUMNS).filter(([name]) => !existing.includes(name));
+  execFileSync('sqlite3', [
+    db,
+    `${missing.map(([name, type]) => `ALTER TABLE pings ADD COLUMN ${name} ${type};`).join('\n')}
+     CREATE INDEX IF NOT EXISTS pings_sent_at ON pings (sent_at);
+     CREATE INDEX IF NOT EXISTS pings_priority_fee ON pings (priority_fee_micro_lamports);
+     CREATE INDEX IF NOT EXISTS pings_tx_signature ON pings (tx_signature);`
+  ]);
+}
+
+export function insertPing(db, record) {
+  const names = Object.keys(COLUMNS);
+  const sql = `INSERT INTO pings (${names.join(', ')}) VALUES (${names.map((n) => sqlValue(record[n])).join(', ')});`;
+  execFile('sqlite3', ['-cmd', '.timeout 5000', db, sql], (e) => {
+    if (e) console.log(`${timestamp()} ERROR: Unable to write ping to ${db}: ${e.message}`);
+  });
+}
+
+// Set columns of the ping with this signature, e.g. once a timed out ping
+// has landed late
+export function updatePing(db, signature, fields) {
+  const assignments = Object.entries(fields).map(([name, value]) => `${name} = ${sqlValue(value)}`);
+  const sql = `UPDATE pings SET ${assignments.join(', ')} WHERE tx_signature = ${sqlValue(signature)};`;
+  execFile('sqlite3', ['-cmd', '.timeout 5000', db, sql], (e) => {
+    if (e) console.log(`${timestamp()} ERROR: Unable to update ping in ${db}: ${e.message}`);
+  });
+}
+
+// Run a query, resolving to an array of row objects.
+export function querySqlite(db, sql) {
+  return new Promise((resolve, reject) => {
+    execFile('sqlite3', ['-json', '-readonly', db, sql], (e, stdout) => {
+      if (e) return reject(e);
+      resolve(stdout.trim() ? JSON.parse(stdout) : []);
+    });
+  });
+}
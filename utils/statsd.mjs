@@ -0,0 +1,57 @@
+// StatsD / DogStatsD metrics backend. Every metric update is sent over UDP as
+// it happens. Metrics computed at scrape time for Prometheus are polled every
+// flushIntervalMs instead. Plain StatsD has no tags, so label values are
+// folded into the metric name.
+import dgram from 'dgram';
+import { addSink, collectMetrics } from './metrics.mjs';
+import { sleep, timestamp } from './misc.mjs';
+
+const TYPE_SUFFIX = { counter: 'c', gauge: 'g', histogram: 'ms' };
+
+function formatLine(flavor, type, name, labels, value) {
+  const entries = Object.entries(labels).filter(([, v]) => v !== undefined);
+  if (flavor === 'dogstatsd') {
+    const tags = entries.map(([k, v]) => `${k}:${String(v).replace(/[|,#]/g, '_')}`);
+    return `${name}:${value}|${TYPE_SUFFIX[type]}${tags.length ? `|#${tags.join(',')}` : ''}`;
+  }
+  const suffix = entries.map(([, v]) => `.${String(v).replace(/[^A-Za-z0-9_-]/g, '_')}`).join('');
+  return `${name}${suffix}:${value}|${TYPE_SUFFIX[type]}`;
+}
+
+// flavor is statsd or dogstatsd. address is host:port.
+export async function runStatsdBackend(flavor, address, flushIntervalMs) {
+  const [host, port] = address.split(':');
+  const socket = dgram.createSocket('udp4');
+  socket.unref();
+
+  const send = (line) => {
+    socket.send(line, Number(port || 8125), host, (e) => {
+      if (e) console.log(`${timestamp()} ERROR: StatsD send failed: ${e.message}`);
+    });
+  };
+
+  addSink({
+    emit(type, name, labels, value) {
+      send(formatLine(flavor, type, name, labels, value));
+    }
+  });
+
+  // Collected counters are running totals, so send the change since the
+  // last flush.
+  const lastTotals = new Map();
+  while (true) {
+    await sleep(flushIntervalMs);
+    collectMetrics().forEach(({ type, name, series }) => {
+      series.forEach(({ labels, value }) => {
+        if (type === 'counter') {
+          const key = `${name}${JSON.stringify(labels)}`;
+          const delta = value - (lastTotals.get(key) || 0);
+          lastTotals.set(key, value);
+          if (delta > 0) send(formatLine(flavor, type, name, labels, delta));
+        } else {
+          send(formatLine(flavor, type, name, labels, value));
+        }
+      });
+    });
+  }
+}
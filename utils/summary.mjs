@@ -0,0 +1,63 @@
+// Periodic health line: confirmation latency quantiles, median slot latency
+// and landing rate over the last interval, logged and exported as gauges.
+import { sleep, timestamp } from './misc.mjs';
+import { median } from './leaderstats.mjs';
+import { percentileOf } from './priorityfees.mjs';
+import { logEvent } from './logger.mjs';
+
+export const SUMMARY_QUANTILES = [50, 90, 99];
+
+// Pings since the last summary
+let pings = [];
+
+// The latest summary, see summarize()
+export const latestSummary = {};
+
+// latencyMs and slotLatency are undefined for pings that didn't land.
+export function recordPingSummary(success, latencyMs, slotLatency) {
+  pings.push({ success, latencyMs, slotLatency });
+}
+
+function summarize(window) {
+  const landed = window.filter((p) => p.success);
+  const latencies = landed.map((p) => p.latencyMs);
+  const slotLatencies = landed
+    .map((p) => p.slotLatency)
+    .filter((l) => l !== undefined);
+  return {
+    pings: window.length,
+    landingRate: window.length ? landed.length / window.length : undefined,
+    latencyMs: Object.fromEntries(
+      SUMMARY_QUANTILES.map((q) => [`p${q}`, percentileOf(latencies, q)])
+    ),
+    medianSlotLatency: median(slotLatencies)
+  };
+}
+
+export async function logLatencySummary(intervalMs) {
+  while (true) {
+    await sleep(intervalMs);
+
+    const window = pings;
+    pings = [];
+    const summary = summarize(window);
+    Object.assign(latestSummary, summary);
+
+    const rate = summary.landingRate === undefined
+      ? 'n/a'
+      : `${(summary.landingRate * 100).toFixed(1)}%`;
+    const quantiles = SUMMARY_QUANTILES
+      .map((q) => `p${q}: ${summary.latencyMs[`p${q}`] ?? 'n/a'}ms`)
+      .join(' ');
+    console.log(`${timestamp()} Summary (last ${Math.round(intervalMs / 1000)}s): pings: ${summary.pings} landing rate: ${rate} ${quantiles} median slot latency: ${summary.medianSlotLatency ?? 'n/a'}`);
+    logEvent('summary', {
+      window_ms: intervalMs,
+      pings: summary.pings,
+      landing_rate: summary.landingRate,
+      latency_p50_ms: summary.latencyMs.p50,
+      latency_p90_ms: summary.latencyMs.p90,
+      latency_p99_ms: summary.latencyMs.p99,
+      median_slot_latency: summary.medianSlotLatency
+    });
+  }
+}
@@ -0,0 +1,36 @@
+// Run background watchers under supervision. A watcher that throws or
+// returns is restarted with backoff instead of silently dying while the
+// ping loop carries on with stale data.
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+import { captureError } from './sentry.mjs';
+import { notify } from './notifier.mjs';
+
+// watcher name => number of restarts
+export const watcherRestarts = {};
+
+// `start` is called with no arguments and should return a promise that only
+// settles if the watcher stops.
+export async function supervise(name, start, baseMs = 1000) {
+  watcherRestarts[name] = 0;
+  let failures = 0;
+
+  while (true) {
+    const startedAt = Date.now();
+    try {
+      await start();
+      console.log(`${timestamp()} ERROR: Watcher ${name} stopped`);
+    } catch (e) {
+      console.log(`${timestamp()} ERROR: Watcher ${name} crashed: ${e.stack || e}`);
+      captureError(e, `watcher:${name}`);
+    }
+
+    // A watcher that ran for a while before stopping starts over from a
+    // short delay
+    failures = Date.now() - startedAt > 60000 ? 1 : failures + 1;
+    watcherRestarts[name]++;
+    const delay = backoffMs(failures, baseMs);
+    console.log(`${timestamp()} Restarting watcher ${name} in ${Math.round(delay)}ms (restarts: ${watcherRestarts[name]})`);
+    notify(`watcher:${name}`, `Watcher ${name} restarted (restarts: ${watcherRestarts[name]})`);
+    await sleep(delay);
+  }
+}
@@ -0,0 +1,48 @@
+// Optional systemd Type=notify support. Node can't write to the notify
+// socket directly, so this shells out to systemd-notify, which needs
+// NotifyAccess=all in the unit file. See ping-thing-client.service.
+import { execFile, execFileSync } from 'child_process';
+import { sleep, timestamp } from './misc.mjs';
+
+const notifySocketSet = () => Boolean(process.env.NOTIFY_SOCKET);
+
+export function sdNotify(state) {
+  if (!notifySocketSet()) return;
+  execFile('systemd-notify', [state], (e) => {
+    if (e) console.log(`${timestamp()} ERROR: systemd-notify ${state} failed: ${e.message}`);
+  });
+}
+
+// Blocking version for use during shutdown, right before process.exit().
+export function sdNotifySync(state) {
+  if (!notifySocketSet()) return;
+  try {
+    execFileSync('systemd-notify', [state]);
+  } catch (e) {
+    console.log(`${timestamp()} ERROR: systemd-notify ${state} failed: ${e.message}`);
+  }
+}
+
+// Send READY=1 once isReady() is true, then keep sending WATCHDOG=1 at half
+// the WatchdogSec interval for as long as isAlive() is true. If the watchers
+// wedge, the keepalives stop and systemd restarts the service.
+export async function runSystemdNotifier(isReady, isAlive) {
+  if (!notifySocketSet()) return;
+
+  while (!isReady()) await sleep(500);
+  sdNotify('READY=1');
+  console.log(`${timestamp()} Notified systemd that we are ready`);
+
+  const watchdogUsec = Number(process.env.WATCHDOG_USEC);
+  if (!watchdogUsec) return;
+
+  const intervalMs = watchdogUsec / 1000 / 2;
+  while (true) {
+    await sleep(intervalMs);
+    if (isAlive()) {
+      sdNotify('WATCHDOG=1');
+    } else {
+      console.log(`${timestamp()} ERROR: Watchers are stale. Skipping systemd watchdog keepalive`);
+    }
+  }
+}
@@ -0,0 +1,105 @@
+// Minimal OpenTelemetry tracing. Spans are batched and exported with
+// OTLP/HTTP JSON to OTLP_ENDPOINT (e.g. http://localhost:4318), which Jaeger,
+// Tempo and the OpenTelemetry collector all accept. When tracing is off, the
+// span methods are no-ops.
+import crypto from 'crypto';
+import { sleep, timestamp } from './misc.mjs';
+
+const SERVICE_NAME = 'ping-thing-client';
+
+let exportEndpoint;
+let resourceAttributes = [];
+let pendingSpans = [];
+
+const randomHex = (bytes) => crypto.randomBytes(bytes).toString('hex');
+const nowNanos = () => (BigInt(Date.now()) * 1000000n).toString();
+
+function toAttributes(attributes) {
+  return Object.entries(attributes)
+    .filter(([, value]) => value !== undefined)
+    .map(([key, value]) => ({
+      key: key,
+      value: typeof value === 'number'
+        ? (Number.isInteger(value) ? { intValue: value } : { doubleValue: value })
+        : typeof value === 'boolean' ? { boolValue: value } : { stringValue: String(value) }
+    }));
+}
+
+function createSpan(name, traceId, parentSpanId, attributes) {
+  const span = {
+    traceId: traceId,
+    spanId: randomHex(8),
+    parentSpanId: parentSpanId,
+    name: name,
+    startTimeUnixNano: nowNanos(),
+    attributes: { ...attributes },
+    ended: false,
+
+    child(childName, childAttributes = {}) {
+      return createSpan(childName, traceId, span.spanId, childAttributes);
+    },
+
+    setAttributes(more) {
+      Object.assign(span.attributes, more);
+    },
+
+    // error marks the span as failed
+    end(error) {
+      if (span.ended || !exportEndpoint) return;
+      span.ended = true;
+      pendingSpans.push({
+        traceId: span.traceId,
+        spanId: span.spanId,
+        parentSpanId: span.parentSpanId,
+        name: span.name,
+        kind: 1,
+        startTimeUnixNano: span.startTimeUnixNano,
+        endTimeUnixNano: nowNanos(),
+        attributes: toAttributes(span.attributes),
+        status: error ? { code: 2, message: error.message } : { code: 1 }
+      });
+    }
+  };
+  return span;
+}
+
+// Start a new trace. Returns its root span.
+export function startTrace(name, attributes = {}) {
+  return createSpan(name, randomHex(16), undefined, attributes);
+}
+
+async function exportSpans() {
+  if (pendingSpans.length === 0) return;
+  const spans = pendingSpans;
+  pendingSpans = [];
+
+  const response = await fetch(`${exportEndpoint}/v1/traces`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({
+      resourceSpans: [{
+        resource: { attributes: resourceAttributes },
+        scopeSpans: [{ scope: { name: SERVICE_NAME }, spans: spans }]
+      }]
+    })
+  });
+  if (!response.ok) throw new Error(`OTLP export returned ${response.status}`);
+}
+
+// Turn tracing on and export batched spans every intervalMs.
+export async function runTraceExporter(endpoint, pingerName, intervalMs) {
+  exportEndpoint = endpoint.replace(/\/$/, '');
+  resourceAttributes = toAttributes({
+    'service.name': SERVICE_NAME,
+    'service.instance.id': pingerName
+  });
+
+  while (true) {
+    await sleep(intervalMs);
+    try {
+      await exportSpans();
+    } catch (e) {
+      console.log(`${timestamp()} ERROR: Unable to export traces: ${e.message}`);
+    }
+  }
+}
@@ -0,0 +1,69 @@
+// The ping transaction: an optional compute budget, then a 5000 lamport
+// transfer from the wallet to itself, optionally padded with a memo. A memo
+// ping sends just the memo instead of the transfer.
+import web3 from '@solana/web3.js';
+import { computeBudgetInstructions } from './priorityfees.mjs';
+
+const MEMO_PROGRAM_ID = new web3.PublicKey('MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr');
+
+// The largest transaction that fits in a packet
+export const PACKET_DATA_SIZE = 1232;
+
+export const TRANSACTION_TYPES = ['transfer', 'memo'];
+
+// Compute units to allow for the memo of a ping, if it has one, on the
+// generous side. The memo program logs the memo, which costs more as it grows.
+export const memoComputeUnits = (bytes, transactionType = 'transfer') =>
+  bytes > 0 || transactionType === 'memo' ? 10000 + 20 * bytes : 0;
+
+// What a ping costs if it lands: the 5000 lamport signature fee plus the
+// priority fee, which is charged on the compute unit limit
+export function estimatePingFee(priorityFee, computeUnitLimit) {
+  return 5000 + (priorityFee ? Math.ceil(priorityFee * computeUnitLimit / 1000000) : 0);
+}
+
+// Serialized size of a transaction signed by keypair alone. The blockhash
+// isn't known yet, but any 32 bytes take the same room.
+function signedSize(transaction, keypair) {
+  transaction.feePayer = keypair.publicKey;
+  transaction.recentBlockhash = keypair.publicKey.toBase58();
+  return 1 + 64 + transaction.serializeMessage().length;
+}
+
+const memoInstruction = (bytes) => new web3.TransactionInstruction({
+  keys: [],
+  programId: MEMO_PROGRAM_ID,
+  data: Buffer.alloc(bytes, 'x')
+});
+
+// How many bytes of memo pad a ping to size. 0 if the ping is already that
+// big.
+export function paddingBytes(keypair, priorityFee, computeUnitLimit, size, transactionType = 'transfer') {
+  const unpadded = buildPingTransaction(keypair, priorityFee, computeUnitLimit, 0, transactionType);
+  if (transactionType === 'transfer') unpadded.add(memoInstruction(0));
+  const bytes = size - signedSize(unpadded, keypair);
+  // The memo length takes a second byte past 127
+  return Math.max(0, bytes > 127 ? bytes - 1 : bytes);
+}
+
+// priorityFee is in micro-lamports per CU. Without one no compute budget
+// instructions are added. memoBytes pads the transaction with a memo, see
+// paddingBytes. The compute unit limit must allow for it, see
+// memoComputeUnits. transactionType is one of TRANSACTION_TYPES.
+export function buildPingTransaction(keypair, priorityFee, computeUnitLimit, memoBytes, transactionType = 'transfer') {
+  const transaction = new web3.Transaction();
+  if (priorityFee !== undefined) {
+    transaction.add(...computeBudgetInstructions(priorityFee, computeUnitLimit));
+  }
+  if (transactionType === 'transfer') {
+    transaction.add(
+      web3.SystemProgram.transfer({
+        fromPubkey: keypair.publicKey,
+        toPubkey: keypair.publicKey,
+        lamports: 5000
+      })
+    );
+  }
+  if (memoBytes > 0 || transactionType === 'memo') transaction.add(memoInstruction(memoBytes));
+  return transaction;
+}
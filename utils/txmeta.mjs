@@ -0,0 +1,49 @@
+// Details of a landed ping that only the ledger knows: compute units
+// consumed, the fee paid and the TX's index within its block. Our web3.js
+// version drops computeUnitsConsumed from getTransaction, so both calls go
+// straight to the RPC endpoint.
+import { rpcHeaders } from './rpcheaders.mjs';
+
+const RPC_TIMEOUT_MS = 10000;
+
+async function rpcRequest(rpcEndpoint, method, params) {
+  const response = await fetch(rpcEndpoint, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json', ...rpcHeaders(rpcEndpoint) },
+    body: JSON.stringify({ jsonrpc: '2.0', id: 1, method, params }),
+    signal: AbortSignal.timeout(RPC_TIMEOUT_MS)
+  });
+  if (!response.ok) throw new Error(`${method} returned ${response.status}`);
+  const body = await response.json();
+  if (body.error) throw new Error(body.error.message);
+  return body.result;
+}
+
+// Resolves to { computeUnitsConsumed, fee, txIndex }. Fields the RPC node
+// can't provide (yet) are undefined.
+export async function fetchTransactionDetails(rpcEndpoint, signature, slot) {
+  const [transaction, block] = await Promise.all([
+    rpcRequest(rpcEndpoint, 'getTransaction', [
+      signature,
+      { commitment: 'confirmed', encoding: 'json', maxSupportedTransactionVersion: 0 }
+    ]),
+    slot === undefined
+      ? undefined
+      : rpcRequest(rpcEndpoint, 'getBlock', [
+          slot,
+          {
+            commitment: 'confirmed',
+            transactionDetails: 'signatures',
+            rewards: false,
+            maxSupportedTransactionVersion: 0
+          }
+        ]).catch(() => undefined)
+  ]);
+  const meta = transaction ? transaction.meta : undefined;
+  const txIndex = block ? block.signatures.indexOf(signature) : -1;
+  return {
+    computeUnitsConsumed: meta ? meta.computeUnitsConsumed : undefined,
+    fee: meta ? meta.fee : undefined,
+    txIndex: txIndex === -1 ? undefined : txIndex
+  };
+}
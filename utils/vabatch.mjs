@@ -0,0 +1,53 @@
+// Batched validators.app submissions. Ping results are collected and posted
+// together to the ping-thing-batch endpoint every interval, or sooner once
+// maxSize are waiting. A batch that fails is handed to the retry queue one
+// payload at a time.
+import { sleep, timestamp } from './misc.mjs';
+import { enqueueVaPayload, isRetryableStatus } from './vaqueue.mjs';
+
+const POST_TIMEOUT_MS = 10000;
+
+let batch = [];
+let flushNow;
+
+export const vaBatchSize = () => batch.length;
+
+export function addToVaBatch(payload, maxSize) {
+  batch.push(payload);
+  if (batch.length >= maxSize && flushNow) flushNow();
+}
+
+async function postBatch(url, apiKey, payloads) {
+  try {
+    const response = await fetch(url, {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json', 'Token': apiKey },
+      body: `{"transactions":[${payloads.join(',')}]}`,
+      signal: AbortSignal.timeout(POST_TIMEOUT_MS)
+    });
+    if (response.ok) return undefined;
+    return { error: new Error(`validators.app returned ${response.status}`), retry: isRetryableStatus(response.status) };
+  } catch (e) {
+    return { error: e, retry: true };
+  }
+}
+
+// onError is called with the error for every failed batch.
+export async function runVaBatcher(url, apiKey, intervalMs, onError) {
+  while (true) {
+    await Promise.race([
+      sleep(intervalMs),
+      new Promise((resolve) => { flushNow = resolve; })
+    ]);
+    if (batch.length === 0) continue;
+
+    const payloads = batch;
+    batch = [];
+    const failure = await postBatch(url, apiKey, payloads);
+    if (failure) {
+      console.log(`${timestamp()} ERROR: Unable to submit a batch of ${payloads.length} to validators.app: ${failure.error.message}`);
+      onError(failure.error);
+      if (failure.retry) payloads.forEach(enqueueVaPayload);
+    }
+  }
+}
@@ -0,0 +1,118 @@
+// Retry queue for validators.app submissions that failed with a network
+// error, a 429 or a 5xx. Payloads are retried in order with backoff. When a
+// file is configured the queue is kept on disk so it survives restarts. The
+// file is a journal, `+<id> <payload>` when a payload is queued and `-<id>`
+// when it leaves the queue, rewritten from the queue every COMPACT_LINES.
+import fs from 'fs';
+import { sleep, timestamp, backoffMs } from './misc.mjs';
+
+const POST_TIMEOUT_MS = 10000;
+const COMPACT_LINES = 10000;
+
+// { id, payload } entries, oldest first
+let queue = [];
+let queueFile;
+let maxEntries = 10000;
+let nextId = 1;
+let journalLines = 0;
+
+export const vaQueueSize = () => queue.length;
+
+// Should a POST that got this HTTP status be retried?
+export const isRetryableStatus = (status) => status === 429 || status >= 500;
+
+function writeJournal(write) {
+  if (!queueFile) return;
+  try {
+    write();
+  } catch (e) {
+    console.log(`${timestamp()} ERROR: Unable to write validators.app retry queue: ${e.message}`);
+  }
+}
+
+function compact() {
+  writeJournal(() => {
+    const tmp = `${queueFile}.tmp`;
+    fs.writeFileSync(tmp, queue.map(({ id, payload }) => `+${id} ${payload}\n`).join(''));
+    fs.renameSync(tmp, queueFile);
+    journalLines = queue.length;
+  });
+}
+
+function appendJournal(line) {
+  if (journalLines >= COMPACT_LINES) return compact();
+  writeJournal(() => {
+    fs.appendFileSync(queueFile, line + '\n');
+    journalLines++;
+  });
+}
+
+function removeEntry(entry) {
+  const i = queue.indexOf(entry);
+  if (i === -1) return;
+  queue.splice(i, 1);
+  appendJournal(`-${entry.id}`);
+}
+
+// Load any payloads left over from a previous run. Files written before the
+// journal format have one bare payload per line.
+export function openVaQueue(file, max) {
+  queueFile = file;
+  maxEntries = max;
+  if (!file || !fs.existsSync(file)) return;
+
+  const entries = new Map();
+  for (const line of fs.readFileSync(file, 'utf8').split('\n')) {
+    const match = line.match(/^([+-])(\d+)(?: (.*))?$/);
+    if (!match) {
+      if (line) entries.set(nextId++, line);
+      continue;
+    }
+    const id = Number(match[2]);
+    if (match[1] === '+') entries.set(id, match[3]);
+    else entries.delete(id);
+    nextId = Math.max(nextId, id + 1);
+  }
+  queue = [...entries].map(([id, payload]) => ({ id, payload }));
+  compact();
+  if (queue.length) console.log(`${timestamp()} Loaded ${queue.length} queued validators.app submissions from ${file}`);
+}
+
+export function enqueueVaPayload(payload) {
+  const entry = { id: nextId++, payload };
+  queue.push(entry);
+  appendJournal(`+${entry.id} ${payload}`);
+  if (queue.length > maxEntries) {
+    removeEntry(queue[0]);
+    console.log(`${timestamp()} WARNING: validators.app retry queue is full, dropped the oldest submission`);
+  }
+}
+
+export async function watchVaQueue(url, apiKey, intervalMs) {
+  let failures = 0;
+  while (true) {
+    await sleep(failures ? backoffMs(failures, intervalMs) : intervalMs);
+    while (queue.length) {
+      // A full queue may drop this entry while it is in flight
+      const entry = queue[0];
+      try {
+        const response = await fetch(url, {
+          method: 'POST',
+          headers: { 'Content-Type': 'application/json', 'Token': apiKey },
+          body: entry.payload,
+          signal: AbortSignal.timeout(POST_TIMEOUT_MS)
+        });
+        if (isRetryableStatus(response.status)) throw new Error(`HTTP ${response.status}`);
+        if (!response.ok) {
+          console.log(`${timestamp()} ERROR: validators.app rejected a queued submission with ${response.status}, dropping it`);
+        }
+      } catch (e) {
+        failures++;
+        console.log(`${timestamp()} ERROR: Unable to resubmit to validators.app (${queue.length} queued, ${failures} failures in a row): ${e.message}`);
+        break;
+      }
+      failures = 0;
+      removeEntry(entry);
+    }
+  }
+}
@@ -0,0 +1,165 @@
+// Load the pinging wallet from WALLET_KEYPAIR_PATH, a Solana CLI keypair file
+// (a JSON array of the 64 secret key bytes, like ~/.config/solana/id.json),
+// or from WALLET_PRIVATE_KEYPAIR, the base58 encoded secret key.
+// WALLET_KEYPAIR_PATHS lists several keypair files to ping from in turn.
+//
+// WALLET_KEYPAIR_PATH may also be an encrypted keypair written by the
+// encrypt-keypair command: the secret key sealed with AES-256-GCM under a key
+// derived from a passphrase with scrypt. The passphrase is read from
+// WALLET_PASSPHRASE_FILE, or prompted for when running in a terminal.
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+import crypto from 'crypto';
+import readline from 'readline';
+import web3 from '@solana/web3.js';
+import bs58 from 'bs58';
+
+// scrypt cost parameters for new files. N = 2^17 takes 128 MiB and about
+// half a second, and is only paid once at startup.
+const SCRYPT_PARAMS = { N: 131072, r: 8, p: 1 };
+
+const expandHome = (file) => file.replace(/^~(?=$|\/)/, os.homedir());
+
+function deriveKey(passphrase, salt, params) {
+  return crypto.scryptSync(passphrase, salt, 32, {
+    ...params,
+    maxmem: 256 * params.N * params.r
+  });
+}
+
+// Prompt on the terminal without echoing. Prompts go to stderr so stdout
+// can be redirected to a file.
+function promptPassphrase(question) {
+  if (!process.stdin.isTTY) {
+    return Promise.reject(new Error('the keypair is encrypted: set WALLET_PASSPHRASE_FILE or run in a terminal'));
+  }
+  const rl = readline.createInterface({ input: process.stdin, output: process.stderr, terminal: true });
+  rl._writeToOutput = (text) => {
+    if (text.startsWith(question)) rl.output.write(question);
+  };
+  return new Promise((resolve) => rl.question(question, (answer) => {
+    rl.close();
+    process.stderr.write('\n');
+    resolve(answer);
+  }));
+}
+
+async function readPassphrase(env, question) {
+  if (env.WALLET_PASSPHRASE_FILE) {
+    return fs.readFileSync(expandHome(env.WALLET_PASSPHRASE_FILE), 'utf8').replace(/\r?\n$/, '');
+  }
+  return promptPassphrase(question);
+}
+
+export function encryptSecretKey(secretKey, passphrase) {
+  const salt = crypto.randomBytes(16);
+  const iv = crypto.randomBytes(12);
+  const key = deriveKey(passphrase, salt, SCRYPT_PARAMS);
+  const cipher = crypto.createCipheriv('aes-256-gcm', key, iv);
+  key.fill(0);
+  const ciphertext = Buffer.concat([cipher.update(secretKey), cipher.final()]);
+  return {
+    version: 1,
+    kdf: 'scrypt',
+    kdfparams: { ...SCRYPT_PARAMS, salt: salt.toString('base64') },
+    cipher: 'aes-256-gcm',
+    iv: iv.toString('base64'),
+    tag: cipher.getAuthTag().toString('base64'),
+    ciphertext: ciphertext.toString('base64')
+  };
+}
+
+// The buffers holding the secret key on the way in are overwritten once the
+// keypair has been built. Some web3.js versions keep a reference to the
+// array they are given instead of copying it, so the keypair gets its own.
+function keypairFrom(secretKey) {
+  try {
+    return web3.Keypair.fromSecretKey(Uint8Array.from(secretKey));
+  } finally {
+    secretKey.fill(0);
+  }
+}
+
+export function decryptSecretKey(file, passphrase) {
+  if (file.kdf !== 'scrypt' || file.cipher !== 'aes-256-gcm') {
+    throw new Error(`unsupported keypair encryption ${file.kdf}/${file.cipher}`);
+  }
+  const { salt, ...params } = file.kdfparams;
+  const key = deriveKey(passphrase, Buffer.from(salt, 'base64'), params);
+  const decipher = crypto.createDecipheriv('aes-256-gcm', key, Buffer.from(file.iv, 'base64'));
+  key.fill(0);
+  decipher.setAuthTag(Buffer.from(file.tag, 'base64'));
+  try {
+    return Buffer.concat([
+      decipher.update(Buffer.from(file.ciphertext, 'base64')),
+      decipher.final()
+    ]);
+  } catch (e) {
+    throw new Error('wrong passphrase for the encrypted keypair');
+  }
+}
+
+export async function loadKeypair(env) {
+  if (env.WALLET_KEYPAIR_PATH) {
+    const file = expandHome(env.WALLET_KEYPAIR_PATH);
+    const contents = JSON.parse(fs.readFileSync(file, 'utf8'));
+    if (contents && contents.ciphertext) {
+      const passphrase = await readPassphrase(env, `Passphrase for ${file}: `);
+      return keypairFrom(decryptSecretKey(contents, passphrase));
+    }
+    if (!Array.isArray(contents) || contents.length !== 64) {
+      throw new Error(`${file} is not a Solana keypair file (expected a JSON array of 64 bytes)`);
+    }
+    const secretKey = Uint8Array.from(contents);
+    contents.fill(0);
+    return keypairFrom(secretKey);
+  }
+  return keypairFrom(bs58.decode(env.WALLET_PRIVATE_KEYPAIR));
+}
+
+// All the pinging wallets as [{ label, keypair }]. Wallets from files are
+// labelled with the file name, e.g. wallet-a for ~/keys/wallet-a.json, and
+// the WALLET_PRIVATE_KEYPAIR wallet with its address.
+export async function loadWallets(env) {
+  const paths = (env.WALLET_KEYPAIR_PATHS || '').split(',').map((p) => p.trim()).filter((p) => p);
+  if (!paths.length) {
+    const keypair = await loadKeypair(env);
+    return [{
+      label: env.WALLET_KEYPAIR_PATH
+        ? path.basename(env.WALLET_KEYPAIR_PATH, '.json')
+        : keypair.publicKey.toBase58(),
+      keypair: keypair
+    }];
+  }
+  const wallets = [];
+  for (const keypairPath of paths) {
+    wallets.push({
+      label: path.basename(keypairPath, '.json'),
+      keypair: await loadKeypair({ ...env, WALLET_KEYPAIR_PATH: keypairPath })
+    });
+  }
+  const labels = wallets.map((w) => w.label);
+  if (new Set(labels).size !== labels.length) {
+    throw new Error(`WALLET_KEYPAIR_PATHS file names must be unique, got ${labels.join(', ')}`);
+  }
+  return wallets;
+}
+
+// The encrypt-keypair command: print the current wallet encrypted under a
+// new passphrase, to be saved and used as WALLET_KEYPAIR_PATH
+export async function encryptKeypairCommand(env) {
+  const keypair = await loadKeypair(env);
+  let passphrase;
+  if (env.WALLET_PASSPHRASE_FILE) {
+    passphrase = await readPassphrase(env);
+  } else {
+    passphrase = await promptPassphrase('New passphrase: ');
+    if (passphrase !== await promptPassphrase('Repeat passphrase: ')) {
+      throw new Error('passphrases do not match');
+    }
+  }
+  if (!passphrase) throw new Error('empty passphrase');
+  console.log(JSON.stringify(encryptSecretKey(keypair.secretKey, passphrase), null, 2));
+  return 0;
+}
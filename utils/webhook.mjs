@@ -0,0 +1,45 @@
+// POST each ping result to an arbitrary collector. The body is the result as
+// JSON, or a template where {{field}} is replaced by the JSON value of that
+// result field (null if unset) and {{result}} by the whole result, e.g.
+// {"sig": {{signature}}, "ms": {{time}}, "raw": {{result}}}
+import { timestamp } from './misc.mjs';
+
+const POST_TIMEOUT_MS = 10000;
+
+export function renderTemplate(template, result) {
+  return template.replace(/\{\{\s*(\w+)\s*\}\}/g, (_, field) => {
+    const value = field === 'result' ? result : result[field];
+    return value === undefined ? 'null' : JSON.stringify(value);
+  });
+}
+
+// PING_WEBHOOK_HEADERS, a JSON object of extra request headers
+export function parseWebhookHeaders(value) {
+  let headers;
+  try {
+    headers = JSON.parse(value);
+  } catch (e) {
+    headers = undefined;
+  }
+  if (!headers || typeof headers !== 'object' || Array.isArray(headers)) {
+    throw new Error('PING_WEBHOOK_HEADERS must be a JSON object, e.g. {"Authorization": "Bearer ..."}');
+  }
+  return headers;
+}
+
+// headers is an object of extra request headers
+export async function postPingResult(url, headers, template, result) {
+  try {
+    const response = await fetch(url, {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json', ...headers },
+      body: template ? renderTemplate(template, result) : JSON.stringify(result),
+      signal: AbortSignal.timeout(POST_TIMEOUT_MS)
+    });
+    if (!response.ok) {
+      console.log(`${timestamp()} ERROR: Ping webhook returned ${response.status}`);
+    }
+  } catch (e) {
+    console.log(`${timestamp()} ERROR: Ping webhook failed: ${e.message}`);
+  }
+}